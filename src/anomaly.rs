@@ -0,0 +1,238 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::Resources,
+    history::{CostSample, History},
+};
+
+#[derive(Debug, Clone)]
+pub struct AnomalyConfig {
+    // Number of past runs in the baseline
+    pub runs: usize,
+    // Minimum z-score against the baseline to report a change
+    pub threshold: f64,
+    // Minimum change against the baseline mean, in percent
+    pub min_change_percent: f64,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            runs: 10,
+            threshold: 3.0,
+            min_change_percent: 20.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Anomaly {
+    // resource_type, tag or account
+    pub dimension: String,
+    pub key: String,
+    // new, increase or decrease
+    pub kind: String,
+    pub baseline_price_per_hour: f64,
+    pub price_per_hour: f64,
+    pub change_percent: f64,
+    // Deviation from the baseline in standard deviations, none for a flat baseline
+    pub score: Option<f64>,
+}
+
+pub struct Anomalies {
+    pub runs: usize,
+    pub anomalies: Vec<Anomaly>,
+}
+
+// Hourly cost of every resource type, tag and account of a run.
+fn group(samples: &[CostSample]) -> BTreeMap<(&'static str, String), f64> {
+    let mut groups = BTreeMap::new();
+    for sample in samples {
+        *groups
+            .entry(("resource_type", sample.resource_type.clone()))
+            .or_default() += sample.price_per_hour;
+        *groups
+            .entry(("account", sample.account_id.clone().unwrap_or_default()))
+            .or_default() += sample.price_per_hour;
+        for (key, value) in &sample.tags {
+            *groups.entry(("tag", format!("{key}={value}"))).or_default() += sample.price_per_hour;
+        }
+    }
+    groups
+}
+
+// Compare the current run with the mean and standard deviation of the baseline runs.
+pub fn detect(
+    baseline: &[Vec<CostSample>],
+    current: &[CostSample],
+    config: &AnomalyConfig,
+) -> Anomalies {
+    let mut anomalies = Vec::new();
+    if baseline.is_empty() {
+        return Anomalies { runs: 0, anomalies };
+    }
+
+    let baseline = baseline.iter().map(|run| group(run)).collect::<Vec<_>>();
+    let current = group(current);
+    let keys = baseline
+        .iter()
+        .flat_map(|run| run.keys())
+        .chain(current.keys())
+        .collect::<BTreeSet<_>>();
+
+    let runs = baseline.len() as f64;
+    for key in keys {
+        // A group missing from a run costs nothing in that run
+        let values = baseline
+            .iter()
+            .map(|run| run.get(key).copied().unwrap_or_default())
+            .collect::<Vec<f64>>();
+        let mean = values.iter().sum::<f64>() / runs;
+        let std_dev = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / runs).sqrt();
+        let price_per_hour = current.get(key).copied().unwrap_or_default();
+        let delta = price_per_hour - mean;
+
+        let (kind, change_percent) = match mean > 0.0 {
+            false if price_per_hour > 0.0 => ("new", 100.0),
+            false => continue,
+            true if delta >= 0.0 => ("increase", delta * 100.0 / mean),
+            true => ("decrease", delta * 100.0 / mean),
+        };
+        // A deviation within the rounding noise of the mean is a flat baseline: it has no
+        // score and any change is judged on the change percent alone
+        let score = (std_dev > mean * 1e-6).then(|| delta / std_dev);
+        if change_percent.abs() < config.min_change_percent
+            || score.is_some_and(|score| score.abs() < config.threshold)
+        {
+            continue;
+        }
+
+        anomalies.push(Anomaly {
+            dimension: key.0.to_string(),
+            key: key.1.clone(),
+            kind: kind.to_string(),
+            baseline_price_per_hour: mean,
+            price_per_hour,
+            change_percent,
+            score,
+        });
+    }
+
+    Anomalies {
+        runs: baseline.len(),
+        anomalies,
+    }
+}
+
+// Compare the resources with the previous runs of the same account and region.
+pub fn detect_from_history(
+    history: &History,
+    resources: &Resources,
+    config: &AnomalyConfig,
+) -> Result<Anomalies, Box<dyn Error>> {
+    let first = resources.resources.first();
+    // Runs recorded after this inventory was read, including itself, are not part of the baseline
+    let read_date = resources
+        .resources
+        .iter()
+        .filter_map(|resource| resource.read_date())
+        .find_map(|read_date| DateTime::parse_from_rfc3339(read_date).ok())
+        .map(|read_date| read_date.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now);
+    let baseline = history.samples(
+        first.and_then(|r| r.account_id()),
+        first.and_then(|r| r.region()),
+        &read_date.to_rfc3339_opts(SecondsFormat::Secs, true),
+        config.runs,
+    )?;
+    Ok(detect(
+        &baseline,
+        &CostSample::from_resources(resources)?,
+        config,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(resource_type: &str, price_per_hour: f64) -> CostSample {
+        CostSample {
+            resource_type: resource_type.to_string(),
+            account_id: Some("1".to_string()),
+            tags: BTreeMap::from([("env".to_string(), "prod".to_string())]),
+            price_per_hour,
+        }
+    }
+
+    fn find<'a>(anomalies: &'a Anomalies, dimension: &str, key: &str) -> Option<&'a Anomaly> {
+        anomalies
+            .anomalies
+            .iter()
+            .find(|anomaly| anomaly.dimension == dimension && anomaly.key == key)
+    }
+
+    #[test]
+    fn flat_baseline_has_no_score() {
+        let baseline = vec![vec![sample("Vm", 1.0)]; 5];
+        let anomalies = detect(&baseline, &[sample("Vm", 2.0)], &AnomalyConfig::default());
+        assert_eq!(anomalies.runs, 5);
+        // Resource type, account and tag groups
+        assert_eq!(anomalies.anomalies.len(), 3);
+        let anomaly = find(&anomalies, "resource_type", "Vm").unwrap();
+        assert_eq!(anomaly.kind, "increase");
+        assert_eq!(anomaly.change_percent, 100.0);
+        assert_eq!(anomaly.score, None);
+        assert!(find(&anomalies, "tag", "env=prod").is_some());
+        assert!(find(&anomalies, "account", "1").is_some());
+
+        let anomalies = detect(&baseline, &[sample("Vm", 1.1)], &AnomalyConfig::default());
+        assert!(anomalies.anomalies.is_empty());
+    }
+
+    #[test]
+    fn scores_against_the_baseline_deviation() {
+        let baseline = [1.0, 1.2, 0.8, 1.0]
+            .map(|price| vec![sample("Vm", price)])
+            .to_vec();
+        let config = AnomalyConfig::default();
+
+        let anomalies = detect(&baseline, &[sample("Vm", 2.0)], &config);
+        let anomaly = find(&anomalies, "resource_type", "Vm").unwrap();
+        let std_dev = 0.02_f64.sqrt();
+        assert!((anomaly.score.unwrap() - 1.0 / std_dev).abs() < 1e-9);
+
+        // Within the threshold
+        let anomalies = detect(&baseline, &[sample("Vm", 1.3)], &config);
+        assert!(anomalies.anomalies.is_empty());
+
+        let anomalies = detect(&baseline, &[], &config);
+        let anomaly = find(&anomalies, "resource_type", "Vm").unwrap();
+        assert_eq!(anomaly.kind, "decrease");
+        assert_eq!(anomaly.change_percent, -100.0);
+    }
+
+    #[test]
+    fn new_groups_and_empty_baseline() {
+        let baseline = vec![vec![sample("Vm", 1.0)]; 3];
+        let anomalies = detect(
+            &baseline,
+            &[sample("Vm", 1.0), sample("Volume", 0.5)],
+            &AnomalyConfig::default(),
+        );
+        let anomaly = find(&anomalies, "resource_type", "Volume").unwrap();
+        assert_eq!(anomaly.kind, "new");
+        assert_eq!(anomaly.baseline_price_per_hour, 0.0);
+        assert!(find(&anomalies, "resource_type", "Vm").is_none());
+
+        let anomalies = detect(&[], &[sample("Vm", 1.0)], &AnomalyConfig::default());
+        assert_eq!(anomalies.runs, 0);
+        assert!(anomalies.anomalies.is_empty());
+    }
+}
@@ -1,8 +1,9 @@
 use std::str::FromStr;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use log::error;
 use osc_cost::core::Resource;
+use osc_cost::forecast::parse_horizon;
 
 pub fn parse() -> Option<Args> {
     Args::parse().validate()
@@ -28,19 +29,132 @@ pub struct Drift {
     pub from_date: Option<String>,
     #[arg(long)]
     pub to_date: Option<String>,
+    // One drift per day, against the inventory archived that day when available
+    #[arg(long, default_value_t = false, requires = "compute_drift")]
+    pub daily: bool,
+    // Inventories saved with --format=json, compared with the consumption of their day
+    #[arg(long, value_name = "FILE", requires = "daily")]
+    pub drift_snapshot: Vec<String>,
+    // One drift per resource, against the consumption billed for its id
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "compute_drift",
+        conflicts_with_all = ["daily", "aggregate"]
+    )]
+    pub per_resource: bool,
+    // Break VMs down by pricing component (vCPU, RAM, license, box, dedicated surcharge)
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "compute_drift",
+        conflicts_with_all = ["daily", "per_resource", "aggregate"]
+    )]
+    pub by_component: bool,
+    // One drift per account, e.g. for chargeback with --overall
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "compute_drift",
+        conflicts_with_all = ["daily", "per_resource", "by_component", "aggregate"]
+    )]
+    pub per_account: bool,
+    // Inventories of the linked accounts saved with --format=json
+    #[arg(long, value_name = "FILE", requires = "per_account")]
+    pub account_input: Vec<String>,
+    // Consumption of all the accounts linked to the paying account
+    #[arg(long, default_value_t = false)]
+    pub overall: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct Push {
+    // Pushgateway base URL, metrics are pushed to <URL>/metrics/job/<JOB>
+    #[arg(long, value_name = "URL")]
+    pub push_gateway: Option<String>,
+    // Prometheus remote-write endpoint
+    #[arg(long, value_name = "URL")]
+    pub remote_write: Option<String>,
+    // OpenTelemetry collector endpoint, OTLP over HTTP with protobuf encoding only (no gRPC),
+    // metrics are sent to <URL>/v1/metrics
+    #[arg(long, value_name = "URL")]
+    pub otlp_endpoint: Option<String>,
+    #[arg(long, default_value = "osc-cost")]
+    pub push_job: String,
+    #[arg(long)]
+    pub push_instance: Option<String>,
+    #[arg(long, value_name = "NAME: VALUE")]
+    pub push_header: Vec<String>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Query the cost history stored with --history-db
+    History(History),
+    /// Forecast the spend from the history or from inventories saved as JSON
+    Forecast(Forecast),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct History {
+    // Dates (YYYY-MM-DD) or RFC3339 timestamps, --to-date is excluded
+    #[arg(long)]
+    pub from_date: Option<String>,
+    #[arg(long)]
+    pub to_date: Option<String>,
+    #[arg(long)]
+    pub account_id: Option<String>,
+    #[arg(long)]
+    pub region: Option<String>,
+    #[arg(long)]
+    pub resource_type: Option<String>,
+    #[arg(long)]
+    pub resource_id: Option<String>,
+    #[arg(long, value_name = "KEY[=VALUE]")]
+    pub tag: Option<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct Forecast {
+    // Projection horizon such as 90d, 12w or 6m
+    #[arg(long, default_value = "90d", value_parser = parse_horizon)]
+    pub horizon: i64,
+    // Inventories saved with --format=json, used instead of --history-db
+    #[arg(long, value_name = "FILE")]
+    pub snapshot: Vec<String>,
+    #[command(flatten)]
+    pub query: History,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct Anomaly {
+    // Compare the inventory with the last runs stored in --history-db
+    #[arg(long, default_value_t = false)]
+    pub detect_anomalies: bool,
+    // Number of past runs in the baseline
+    #[arg(long, default_value_t = 10)]
+    pub anomaly_runs: usize,
+    // Minimum deviation from the baseline, in standard deviations
+    #[arg(long, default_value_t = 3.0)]
+    pub anomaly_threshold: f64,
+    // Minimum change from the baseline, in percent
+    #[arg(long, default_value_t = 20.0)]
+    pub anomaly_min_change: f64,
 }
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about=None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     // Profile name to use in ~/.osc/config.json
     #[arg(long, short = 'p')]
     pub profile: Option<String>,
     #[arg(value_enum, long)]
     pub source: Option<InputSource>,
-    #[arg(value_enum, long, default_value_t = OutputFormat::Human)]
+    #[arg(value_enum, long, global = true, default_value_t = OutputFormat::Human)]
     pub format: OutputFormat,
-    #[arg(long, short = 'o')]
+    #[arg(long, short = 'o', global = true)]
     pub output: Option<String>,
     #[arg(long, short = 'i')]
     pub input: Option<String>,
@@ -48,12 +162,34 @@ pub struct Args {
     pub filter: Option<Filter>,
     #[arg(long, short = 'a', default_value_t = false)]
     pub aggregate: bool,
+    // Resource tags written as point tags with --format=influx
+    #[arg(long, value_name = "KEY")]
+    pub influx_tag: Vec<String>,
+    // Index of the --format=es-bulk documents, strftime pattern applied to their read date
+    #[arg(long, default_value = "osc-cost-%Y.%m.%d")]
+    pub es_index: String,
+    // Elasticsearch/OpenSearch base URL, the documents are also sent to <URL>/_bulk
+    #[arg(long, value_name = "URL")]
+    pub es_url: Option<String>,
+    // Extra HTTP headers of the --es-url requests, e.g. "Authorization: ApiKey <key>"
+    #[arg(long, value_name = "NAME: VALUE")]
+    pub es_header: Vec<String>,
     #[arg(long, short = 'n', default_value_t = false)]
     pub need_default_resource: bool,
     #[arg(long, default_value_t = false)]
     pub help_resources: bool,
     #[command(flatten)]
     pub drift: Drift,
+    // Price the consumption since the 1st of the month and project the remaining days
+    #[arg(long, default_value_t = false, conflicts_with = "compute_drift")]
+    pub month_to_date: bool,
+    #[command(flatten)]
+    pub push: Push,
+    #[command(flatten)]
+    pub anomaly: Anomaly,
+    // SQLite database where each run appends the priced resources
+    #[arg(long, global = true, value_name = "FILE", env = "OSC_COST_HISTORY_DB")]
+    pub history_db: Option<String>,
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
@@ -68,7 +204,15 @@ pub enum OutputFormat {
     Month,
     Year,
     Json,
+    JsonReport,
     Ods,
+    Xlsx,
+    Csv,
+    Html,
+    Parquet,
+    Influx,
+    Focus,
+    EsBulk,
     Human,
     Markdown,
     Prometheus,
@@ -93,9 +237,17 @@ impl Args {
         err_count += match (&self.aggregate, &self.format) {
             (false, _) => 0,
             (true, OutputFormat::Json) => 0,
+            (true, OutputFormat::JsonReport) => 0,
             (true, OutputFormat::Human) => 0,
             (true, OutputFormat::Markdown) => 0,
             (true, OutputFormat::Ods) => 0,
+            (true, OutputFormat::Xlsx) => 0,
+            (true, OutputFormat::Csv) => 0,
+            (true, OutputFormat::Html) => 0,
+            (true, OutputFormat::Parquet) => 0,
+            (true, OutputFormat::Influx) => 0,
+            (true, OutputFormat::Focus) => 0,
+            (true, OutputFormat::EsBulk) => 0,
             (true, OutputFormat::Prometheus) => 0,
             (true, OutputFormat::Hour) => {
                 error!("cannot aggregate with hour format");
@@ -116,6 +268,14 @@ impl Args {
                 error!("cannot print ods to the standard output");
                 1
             }
+            (None, OutputFormat::Xlsx) => {
+                error!("cannot print xlsx to the standard output");
+                1
+            }
+            (None, OutputFormat::Parquet) => {
+                error!("cannot print parquet to the standard output");
+                1
+            }
             _ => 0,
         };
 
@@ -134,12 +294,131 @@ impl Args {
             (true, OutputFormat::Json) => 0,
             (true, OutputFormat::Human) => 0,
             (true, OutputFormat::Markdown) => 0,
+            (true, OutputFormat::Prometheus) => 0,
+            (true, OutputFormat::Ods) => 0,
             (true, _) => {
                 error!("cannot use drift with the specified output");
                 1
             }
         };
 
+        if self.drift.daily && matches!(self.format, OutputFormat::Prometheus) {
+            error!("cannot use daily drift with the prometheus output");
+            err_count += 1;
+        }
+
+        if self.drift.per_resource && matches!(self.format, OutputFormat::Prometheus) {
+            error!("cannot use per resource drift with the prometheus output");
+            err_count += 1;
+        }
+
+        if self.drift.per_account && matches!(self.format, OutputFormat::Prometheus) {
+            error!("cannot use per account drift with the prometheus output");
+            err_count += 1;
+        }
+
+        if matches!(self.format, OutputFormat::Ods)
+            && (self.drift.daily
+                || self.drift.per_resource
+                || self.drift.by_component
+                || self.drift.per_account)
+        {
+            error!("the ods output only supports the drift per resource type");
+            err_count += 1;
+        }
+        if self.es_url.is_some() && !matches!(self.format, OutputFormat::EsBulk) {
+            error!("--es-url requires the es-bulk format");
+            err_count += 1;
+        }
+        if !self.es_header.is_empty() && self.es_url.is_none() {
+            error!("--es-header requires --es-url");
+            err_count += 1;
+        }
+        if matches!(self.format, OutputFormat::Focus)
+            && (self.drift.from_date.is_none() || self.drift.to_date.is_none())
+        {
+            error!("you must specified --from-date and --to-date with the focus output");
+            err_count += 1;
+        }
+        if self.drift.overall
+            && !self.drift.compute_drift
+            && !self.month_to_date
+            && !matches!(self.format, OutputFormat::Focus)
+        {
+            error!("--overall requires --compute-drift, --month-to-date or the focus output");
+            err_count += 1;
+        }
+
+        if self.month_to_date
+            && !matches!(
+                self.format,
+                OutputFormat::Human | OutputFormat::Markdown | OutputFormat::Json
+            )
+        {
+            error!("cannot use month to date with the specified output");
+            err_count += 1;
+        }
+
+        if (self.push.push_gateway.is_some()
+            || self.push.remote_write.is_some()
+            || self.push.otlp_endpoint.is_some())
+            && (self.drift.compute_drift
+                || self.month_to_date
+                || !matches!(self.format, OutputFormat::Prometheus))
+        {
+            error!("pushing metrics requires the prometheus format");
+            err_count += 1;
+        }
+
+        if self.anomaly.detect_anomalies && (self.history_db.is_none() || self.drift.compute_drift)
+        {
+            error!("detecting anomalies requires --history-db and cannot be used with drift");
+            err_count += 1;
+        }
+
+        // The baseline is made of unfiltered inventories, a partial one would only decrease
+        if self.anomaly.detect_anomalies && (self.input.is_some() || self.filter.is_some()) {
+            error!("detecting anomalies cannot be used with --input or filters");
+            err_count += 1;
+        }
+
+        let history = match &self.command {
+            Some(Command::History(history)) => {
+                if self.history_db.is_none() {
+                    error!("you must specify --history-db to query the history");
+                    err_count += 1;
+                }
+                Some(history)
+            }
+            Some(Command::Forecast(forecast)) => {
+                if self.history_db.is_none() && forecast.snapshot.is_empty() {
+                    error!("you must specify --history-db or --snapshot to forecast");
+                    err_count += 1;
+                }
+                Some(&forecast.query)
+            }
+            None => None,
+        };
+        if let Some(history) = history {
+            if !matches!(
+                self.format,
+                OutputFormat::Human | OutputFormat::Markdown | OutputFormat::Json
+            ) {
+                error!("cannot use history with the specified output");
+                err_count += 1;
+            }
+            if let Some(resource_type) = &history.resource_type {
+                // Aggregates are not recorded, they are not a resource type of the history
+                if matches!(
+                    Resource::from_str(resource_type),
+                    Err(_) | Ok(Resource::Aggregate(_))
+                ) {
+                    error!("unknown resource type {}", resource_type);
+                    err_count += 1;
+                }
+            }
+        }
+
         if self.drift.compute_drift
             && (self.drift.to_date.is_none() || self.drift.from_date.is_none())
         {
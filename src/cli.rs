@@ -1,15 +1,29 @@
 use args::OutputFormat;
+use chrono::{DateTime, Days, NaiveDate, SecondsFormat, Utc};
 use log::{error, warn};
-use osc_cost::core::digest::{compute_drift, Digest};
+use osc_cost::anomaly::{detect_from_history, Anomalies, AnomalyConfig};
+use osc_cost::core::focus::{estimate_charges, Charges};
 use osc_cost::core::{Resource, Resources};
+use osc_cost::forecast::forecast;
+use osc_cost::history::{CostHistory, History, HistoryQuery};
 use osc_cost::oapi::{Filter, Input};
+use output::csv::csv;
+use output::es::{es_bulk, post_bulk};
+use output::focus::focus;
+use output::html::{html, Options};
 use output::human::Human;
-use output::json::Json;
+use output::influx::influx;
+use output::json::{json_report, Json, ReportFilters, ReportMetadata};
 use output::markdown::Markdown;
 use output::ods::ods;
-use output::prometheus::prometheus;
+use output::parquet::parquet;
+use output::prometheus::otlp::otlp;
+use output::prometheus::push::{push_gateway, remote_write, PushConfig};
+use output::prometheus::{anomaly_metric_families, drift_metric_families, encode, metric_families};
+use output::xlsx::xlsx;
+use prometheus::proto::MetricFamily;
 use serde_json::Deserializer;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::error::{self, Error};
 use std::fs::{self, File};
 use std::io::{BufReader, Write};
@@ -29,20 +43,48 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     if args.help_resources {
         print_managed_resources_help();
-    } else {
-        let mut resources = match args.input {
-            Some(input_file) => {
-                let reader = BufReader::new(File::open(input_file)?);
-                let stream = Deserializer::from_reader(reader).into_iter::<Resource>();
-
-                Resources {
-                    resources: stream
-                        .map(|value| value.expect("while reading input"))
-                        .collect::<Vec<Resource>>(),
+    } else if let Some(args::Command::History(query)) = &args.command {
+        let history = History::open(args.history_db.as_deref().unwrap_or_default())?;
+        let history = history.query(&history_query(query))?;
+        let output = match args.format {
+            OutputFormat::Json => history.json()?.into_bytes(),
+            OutputFormat::Markdown => history.markdown()?.into_bytes(),
+            _ => history.human()?.into_bytes(),
+        };
+        write_output(args.output, output);
+    } else if let Some(args::Command::Forecast(forecast_args)) = &args.command {
+        let query = history_query(&forecast_args.query);
+        let history = match &args.history_db {
+            Some(history_db) => History::open(history_db)?.query(&query)?,
+            None => {
+                let mut snapshots = Vec::new();
+                for snapshot in &forecast_args.snapshot {
+                    let mut resources = read_resources(snapshot)?;
+                    resources.compute()?;
+                    snapshots.push(resources);
                 }
+                CostHistory::from_snapshots(&snapshots, &query)?
             }
+        };
+        let forecast = forecast(&history, Utc::now(), forecast_args.horizon);
+        let output = match args.format {
+            OutputFormat::Json => forecast.json()?.into_bytes(),
+            OutputFormat::Markdown => forecast.markdown()?.into_bytes(),
+            _ => forecast.human()?.into_bytes(),
+        };
+        write_output(args.output, output);
+    } else {
+        // Filters, catalog date and failures of the fetch, for the report metadata
+        let mut filter = None;
+        let mut catalog_fetched_at = None;
+        let mut oos_fetch_failed = false;
+        // Inventories read from a file or filtered are not snapshots of the account
+        let live_inventory = args.input.is_none() && args.filter.is_none();
+        let mut resources = match args.input {
+            Some(input_file) => read_resources(&input_file)?,
             None => {
                 let mut oapi_input = Input::new(args.profile.clone())?;
+                filter = args.filter.clone();
                 oapi_input.filters = match args.filter {
                     None => None,
                     Some(f) => Some(Filter {
@@ -55,73 +97,330 @@ fn main() -> Result<(), Box<dyn Error>> {
                 oapi_input.need_default_resource = need_default_resource;
 
                 oapi_input.fetch()?;
+                catalog_fetched_at = oapi_input.catalog_fetched_at;
+                oos_fetch_failed = oapi_input.oos_fetch_failed;
                 Resources::from(oapi_input)
             }
         };
 
         resources.compute()?;
 
+        let mut anomalies = None;
+        if let Some(history_db) = &args.history_db {
+            let mut history = History::open(history_db)?;
+            if args.anomaly.detect_anomalies {
+                let config = AnomalyConfig {
+                    runs: args.anomaly.anomaly_runs,
+                    threshold: args.anomaly.anomaly_threshold,
+                    min_change_percent: args.anomaly.anomaly_min_change,
+                };
+                let found = detect_from_history(&history, &resources, &config)?;
+                for anomaly in &found.anomalies {
+                    warn!(
+                        "cost anomaly on {} {}: {} of {:+.0}%",
+                        anomaly.dimension, anomaly.key, anomaly.kind, anomaly.change_percent
+                    );
+                }
+                anomalies = Some(found);
+            }
+            match live_inventory {
+                true => history.record(&resources, Utc::now())?,
+                false => warn!("only unfiltered inventories fetched from the API are recorded"),
+            }
+        }
+
         if args.aggregate {
             resources = resources.aggregate();
         }
 
         let output: Vec<u8>;
-        if args.drift.compute_drift {
+        if args.drift.compute_drift && args.drift.daily {
             resources = resources.aggregate();
 
+            let from_date = NaiveDate::parse_from_str(
+                args.drift.from_date.as_deref().unwrap_or_default(),
+                "%Y-%m-%d",
+            )?;
+            let to_date = NaiveDate::parse_from_str(
+                args.drift.to_date.as_deref().unwrap_or_default(),
+                "%Y-%m-%d",
+            )?;
+            let inventories = daily_inventories(
+                args.history_db.as_deref(),
+                &args.drift.drift_snapshot,
+                &resources,
+                from_date,
+                to_date,
+            )?;
+            let mut oapi_input = Input::new(args.profile.clone())?;
+            oapi_input.overall = args.drift.overall;
+            let drifts = oapi_input.daily_drift(&inventories, &resources, from_date, to_date)?;
+
+            output = match args.format {
+                OutputFormat::Json => drifts.json()?.into_bytes(),
+                OutputFormat::Markdown => drifts.markdown()?.into_bytes(),
+                _ => drifts.human()?.into_bytes(),
+            };
+        } else if args.drift.compute_drift && args.drift.per_account {
+            for account_input in &args.drift.account_input {
+                let mut account_resources = read_resources(account_input)?;
+                account_resources.compute()?;
+                resources.resources.extend(account_resources.resources);
+            }
+            let mut oapi_input = Input::new(args.profile.clone())?;
+            oapi_input.overall = args.drift.overall;
+            let drifts = oapi_input.account_drift(
+                resources,
+                args.drift.from_date.as_deref().unwrap_or_default(),
+                args.drift.to_date.as_deref().unwrap_or_default(),
+            )?;
+
+            output = match args.format {
+                OutputFormat::Json => drifts.json()?.into_bytes(),
+                OutputFormat::Markdown => drifts.markdown()?.into_bytes(),
+                _ => drifts.human()?.into_bytes(),
+            };
+        } else if args.drift.compute_drift && args.drift.per_resource {
             let mut oapi_input = Input::new(args.profile.clone())?;
-            oapi_input.fetch_catalog()?;
-            oapi_input.fetch_vm_types()?; // needed to extract information from boxes
-            oapi_input.fetch_digest(
-                &args.drift.from_date.clone().unwrap(),
-                &args.drift.to_date.clone().unwrap(),
+            oapi_input.overall = args.drift.overall;
+            let drifts = oapi_input.resource_drift(
+                &resources,
+                args.drift.from_date.as_deref().unwrap_or_default(),
+                args.drift.to_date.as_deref().unwrap_or_default(),
             )?;
-            let mut digests = HashMap::<String, Digest>::new();
-            oapi_input.fill_digest(&mut digests);
 
-            let drifts = compute_drift(
-                digests,
+            output = match args.format {
+                OutputFormat::Json => drifts.json()?.into_bytes(),
+                OutputFormat::Markdown => drifts.markdown()?.into_bytes(),
+                _ => drifts.human()?.into_bytes(),
+            };
+        } else if args.drift.compute_drift && args.drift.by_component {
+            let mut oapi_input = Input::new(args.profile.clone())?;
+            oapi_input.overall = args.drift.overall;
+            let drifts = oapi_input.component_drift(
                 &resources,
-                args.drift.from_date.unwrap().as_str(),
-                args.drift.to_date.unwrap().as_str(),
-            )
-            .expect("Error while computing the drift");
+                args.drift.from_date.as_deref().unwrap_or_default(),
+                args.drift.to_date.as_deref().unwrap_or_default(),
+            )?;
+
+            output = match args.format {
+                OutputFormat::Json => drifts.json()?.into_bytes(),
+                OutputFormat::Markdown => drifts.markdown()?.into_bytes(),
+                OutputFormat::Prometheus => encode(&drift_metric_families(&drifts)?)?.into_bytes(),
+                _ => drifts.human()?.into_bytes(),
+            };
+        } else if args.drift.compute_drift {
+            resources = resources.aggregate();
+
+            let mut oapi_input = Input::new(args.profile.clone())?;
+            oapi_input.overall = args.drift.overall;
+            let drifts = oapi_input
+                .drift(
+                    &resources,
+                    args.drift.from_date.unwrap().as_str(),
+                    args.drift.to_date.unwrap().as_str(),
+                )
+                .expect("Error while computing the drift");
 
             output = match args.format {
                 OutputFormat::Json => drifts.json()?.into_bytes(),
                 OutputFormat::Human => drifts.human()?.into_bytes(),
                 OutputFormat::Markdown => drifts.markdown()?.into_bytes(),
+                OutputFormat::Prometheus => encode(&drift_metric_families(&drifts)?)?.into_bytes(),
+                OutputFormat::Ods => ods(&resources, Some(&drifts))?,
                 _ => {
                     warn!("unimplemented output for drift computation");
                     exit(1);
                 }
             };
+        } else if args.month_to_date {
+            let mut oapi_input = Input::new(args.profile.clone())?;
+            oapi_input.overall = args.drift.overall;
+            let month_to_date =
+                oapi_input.month_to_date(&resources.aggregate(), Utc::now().date_naive())?;
+
+            output = match args.format {
+                OutputFormat::Json => month_to_date.json()?.into_bytes(),
+                OutputFormat::Markdown => month_to_date.markdown()?.into_bytes(),
+                _ => month_to_date.human()?.into_bytes(),
+            };
         } else {
+            let push_config = PushConfig {
+                job: args.push.push_job,
+                instance: args.push.push_instance,
+                headers: args.push.push_header,
+            };
+            if let Some(url) = &args.push.push_gateway {
+                let metric_families = prometheus_families(&resources, anomalies.as_ref())?;
+                push_gateway(url, &push_config, &metric_families)?;
+            }
+            if let Some(url) = &args.push.remote_write {
+                let metric_families = prometheus_families(&resources, anomalies.as_ref())?;
+                remote_write(url, &push_config, &metric_families)?;
+            }
+            if let Some(url) = &args.push.otlp_endpoint {
+                let metric_families = prometheus_families(&resources, anomalies.as_ref())?;
+                otlp(url, &push_config, &metric_families)?;
+            }
+
             output = match args.format {
                 OutputFormat::Hour => format!("{}", resources.cost_per_hour()?).into_bytes(),
                 OutputFormat::Month => format!("{}", resources.cost_per_month()?).into_bytes(),
                 OutputFormat::Year => format!("{}", resources.cost_per_year()?).into_bytes(),
                 OutputFormat::Json => resources.json()?.into_bytes(),
-                OutputFormat::Prometheus => (prometheus(&resources)?).into_bytes(),
-                OutputFormat::Ods => ods(&resources)?,
-                OutputFormat::Human => resources.aggregate().human()?.into_bytes(),
-                OutputFormat::Markdown => resources.aggregate().markdown()?.into_bytes(),
+                OutputFormat::JsonReport => {
+                    let mut metadata = ReportMetadata::new(&resources);
+                    if let Some(filter) = filter {
+                        metadata.filters = ReportFilters {
+                            tag_keys: filter.filter_tag_key,
+                            tag_values: filter.filter_tag_value,
+                            tags: filter.filter_tag,
+                        };
+                        metadata.skipped_resource_types = filter.skip_resource;
+                    }
+                    metadata.oos_fetch_failed = oos_fetch_failed;
+                    metadata.catalog_fetched_at = catalog_fetched_at
+                        .map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true));
+                    json_report(&resources, metadata)?.into_bytes()
+                }
+                OutputFormat::Prometheus => {
+                    encode(&prometheus_families(&resources, anomalies.as_ref())?)?.into_bytes()
+                }
+                OutputFormat::Ods => ods(&resources, None)?,
+                OutputFormat::Xlsx => xlsx(&resources)?,
+                OutputFormat::Csv => csv(&resources)?,
+                OutputFormat::Html => html(&resources, &Options::default())?.into_bytes(),
+                OutputFormat::Parquet => parquet(&resources)?,
+                OutputFormat::Influx => influx(&resources, &args.influx_tag)?.into_bytes(),
+                OutputFormat::EsBulk => {
+                    let bulk = es_bulk(&resources, &args.es_index)?;
+                    if let Some(url) = &args.es_url {
+                        post_bulk(url, &args.es_header, bulk.clone())?;
+                    }
+                    bulk.into_bytes()
+                }
+                OutputFormat::Focus => {
+                    let from_date = args.drift.from_date.as_deref().unwrap_or_default();
+                    let to_date = args.drift.to_date.as_deref().unwrap_or_default();
+                    let mut oapi_input = Input::new(args.profile.clone())?;
+                    oapi_input.overall = args.drift.overall;
+                    let mut charges = estimate_charges(&resources, from_date, to_date)?;
+                    charges.extend(oapi_input.consumption_charges(from_date, to_date)?);
+                    focus(&Charges { charges })?
+                }
+                OutputFormat::Human => {
+                    let mut out = resources.aggregate().human()?;
+                    if let Some(anomalies) = &anomalies {
+                        out.push_str(&format!("\n\nAnomalies:\n{}", anomalies.human()?));
+                    }
+                    out.into_bytes()
+                }
+                OutputFormat::Markdown => {
+                    let mut out = resources.aggregate().markdown()?;
+                    if let Some(anomalies) = &anomalies {
+                        out.push_str(&format!("\n\nAnomalies:\n{}", anomalies.markdown()?));
+                    }
+                    out.into_bytes()
+                }
             };
         }
 
-        match args.output {
-            Some(output_file) => {
-                write_to_file(&output_file, output).unwrap_or_else(|error| {
-                    error!("Problem writing output to the file: {:?}", error);
-                    exit(1);
-                });
+        write_output(args.output, output);
+    }
+    Ok(())
+}
+
+// Aggregated inventories of each day of the period, from the JSON files or the history.
+fn daily_inventories(
+    history_db: Option<&str>,
+    snapshots: &[String],
+    current: &Resources,
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+) -> Result<BTreeMap<NaiveDate, Resources>, Box<dyn Error>> {
+    let mut inventories = BTreeMap::new();
+    for snapshot in snapshots {
+        let mut resources = read_resources(snapshot)?;
+        let Some(read_date) = resources
+            .resources
+            .iter()
+            .filter_map(|resource| resource.read_date())
+            .find_map(|read_date| DateTime::parse_from_rfc3339(read_date).ok())
+        else {
+            warn!("skipping {snapshot}, it has no read date");
+            continue;
+        };
+        resources.compute()?;
+        inventories.insert(read_date.date_naive(), resources.aggregate());
+    }
+
+    if let Some(history_db) = history_db {
+        let history = History::open(history_db)?;
+        let first = current.resources.first();
+        for date in from_date.iter_days().take_while(|date| *date < to_date) {
+            if inventories.contains_key(&date) {
+                continue;
             }
-            None => {
-                println!("{}", String::from_utf8_lossy(&output));
+            let inventory = history.inventory(
+                first.and_then(|r| r.account_id()),
+                first.and_then(|r| r.region()),
+                &date.to_string(),
+                &(date + Days::new(1)).to_string(),
+            )?;
+            if let Some(resources) = inventory {
+                inventories.insert(date, resources.aggregate());
             }
         }
     }
-    Ok(())
+    Ok(inventories)
+}
+
+fn prometheus_families(
+    resources: &Resources,
+    anomalies: Option<&Anomalies>,
+) -> Result<Vec<MetricFamily>, Box<dyn Error>> {
+    let mut families = metric_families(resources)?;
+    if let Some(anomalies) = anomalies {
+        families.extend(anomaly_metric_families(anomalies)?);
+    }
+    Ok(families)
+}
+
+fn read_resources(input_file: &str) -> Result<Resources, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(input_file)?);
+    let stream = Deserializer::from_reader(reader).into_iter::<Resource>();
+
+    Ok(Resources {
+        resources: stream
+            .map(|value| value.expect("while reading input"))
+            .collect::<Vec<Resource>>(),
+    })
+}
+
+fn history_query(query: &args::History) -> HistoryQuery {
+    HistoryQuery {
+        from_date: query.from_date.clone(),
+        to_date: query.to_date.clone(),
+        account_id: query.account_id.clone(),
+        region: query.region.clone(),
+        resource_type: query.resource_type.clone(),
+        resource_id: query.resource_id.clone(),
+        tag: query.tag.clone(),
+    }
+}
+
+fn write_output(output_file: Option<String>, output: Vec<u8>) {
+    match output_file {
+        Some(output_file) => {
+            write_to_file(&output_file, output).unwrap_or_else(|error| {
+                error!("Problem writing output to the file: {:?}", error);
+                exit(1);
+            });
+        }
+        None => {
+            println!("{}", String::from_utf8_lossy(&output));
+        }
+    }
 }
 
 fn write_to_file(file_path: &str, data: Vec<u8>) -> Result<(), Box<dyn error::Error>> {
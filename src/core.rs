@@ -1,6 +1,6 @@
 use serde::Deserialize;
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::error;
 use std::fmt;
 use strum_macros::EnumString;
@@ -16,11 +16,16 @@ use self::vms::Vm;
 use self::volumes::Volume;
 use self::vpn::Vpn;
 
-static HOURS_PER_MONTH: f32 = (365_f32 * 24_f32) / 12_f32;
+// Resource tags by key, sorted
+pub type Tags = BTreeMap<String, String>;
 
+pub static HOURS_PER_MONTH: f32 = (365_f32 * 24_f32) / 12_f32;
+
+pub mod currency;
 pub mod dedicated_instances;
 pub mod digest;
 pub mod flexible_gpus;
+pub mod focus;
 pub mod load_balancers;
 pub mod nat_services;
 pub mod oos;
@@ -46,6 +51,138 @@ pub enum Resource {
     DedicatedInstance(DedicatedInstance),
 }
 
+impl Resource {
+    pub fn resource_type(&self) -> &str {
+        match self {
+            Resource::Vm(_) => "Vm",
+            Resource::Volume(_) => "Volume",
+            Resource::PublicIp(_) => "PublicIp",
+            Resource::Snapshot(_) => "Snapshot",
+            Resource::NatServices(_) => "NatServices",
+            Resource::Aggregate(aggregate) => &aggregate.aggregated_resource_type,
+            Resource::FlexibleGpu(_) => "FlexibleGpu",
+            Resource::LoadBalancer(_) => "LoadBalancer",
+            Resource::Vpn(_) => "Vpn",
+            Resource::Oos(_) => "Oos",
+            Resource::DedicatedInstance(_) => "DedicatedInstance",
+        }
+    }
+
+    pub fn resource_id(&self) -> Option<&str> {
+        match self {
+            Resource::Vm(vm) => vm.resource_id.as_deref(),
+            Resource::Volume(volume) => volume.resource_id.as_deref(),
+            Resource::PublicIp(public_ip) => public_ip.resource_id.as_deref(),
+            Resource::Snapshot(snapshot) => snapshot.resource_id.as_deref(),
+            Resource::NatServices(nat_service) => nat_service.resource_id.as_deref(),
+            Resource::Aggregate(_) => None,
+            Resource::FlexibleGpu(flexible_gpu) => flexible_gpu.resource_id.as_deref(),
+            Resource::LoadBalancer(load_balancer) => load_balancer.resource_id.as_deref(),
+            Resource::Vpn(vpn) => vpn.resource_id.as_deref(),
+            Resource::Oos(oos) => oos.resource_id.as_deref(),
+            Resource::DedicatedInstance(_) => None,
+        }
+    }
+
+    pub fn tags(&self) -> Option<&Tags> {
+        match self {
+            Resource::Vm(vm) => vm.tags.as_ref(),
+            Resource::Volume(volume) => volume.tags.as_ref(),
+            Resource::PublicIp(public_ip) => public_ip.tags.as_ref(),
+            Resource::Snapshot(snapshot) => snapshot.tags.as_ref(),
+            Resource::NatServices(nat_service) => nat_service.tags.as_ref(),
+            Resource::FlexibleGpu(flexible_gpu) => flexible_gpu.tags.as_ref(),
+            Resource::LoadBalancer(load_balancer) => load_balancer.tags.as_ref(),
+            Resource::Vpn(vpn) => vpn.tags.as_ref(),
+            Resource::Aggregate(_) | Resource::Oos(_) | Resource::DedicatedInstance(_) => None,
+        }
+    }
+
+    pub fn read_date(&self) -> Option<&str> {
+        match self {
+            Resource::Vm(vm) => vm.read_date_rfc3339.as_deref(),
+            Resource::Volume(volume) => volume.read_date_rfc3339.as_deref(),
+            Resource::PublicIp(pip) => pip.read_date_rfc3339.as_deref(),
+            Resource::Snapshot(snapshot) => snapshot.read_date_rfc3339.as_deref(),
+            Resource::NatServices(nat_services) => nat_services.read_date_rfc3339.as_deref(),
+            Resource::Aggregate(aggregate) => aggregate.read_date_rfc3339.as_deref(),
+            Resource::FlexibleGpu(flexible_gpu) => flexible_gpu.read_date_rfc3339.as_deref(),
+            Resource::LoadBalancer(load_balancer) => load_balancer.read_date_rfc3339.as_deref(),
+            Resource::Vpn(vpn) => vpn.read_date_rfc3339.as_deref(),
+            Resource::Oos(oos) => oos.read_date_rfc3339.as_deref(),
+            Resource::DedicatedInstance(dedicated_instance) => {
+                dedicated_instance.read_date_rfc3339.as_deref()
+            }
+        }
+    }
+
+    pub fn account_id(&self) -> Option<&str> {
+        match self {
+            Resource::Vm(vm) => vm.account_id.as_deref(),
+            Resource::Volume(volume) => volume.account_id.as_deref(),
+            Resource::PublicIp(pip) => pip.account_id.as_deref(),
+            Resource::Snapshot(snapshot) => snapshot.account_id.as_deref(),
+            Resource::NatServices(nat_services) => nat_services.account_id.as_deref(),
+            Resource::Aggregate(aggregate) => aggregate.account_id.as_deref(),
+            Resource::FlexibleGpu(flexible_gpu) => flexible_gpu.account_id.as_deref(),
+            Resource::LoadBalancer(load_balancer) => load_balancer.account_id.as_deref(),
+            Resource::Vpn(vpn) => vpn.account_id.as_deref(),
+            Resource::Oos(oos) => oos.account_id.as_deref(),
+            Resource::DedicatedInstance(dedicated_instance) => {
+                dedicated_instance.account_id.as_deref()
+            }
+        }
+    }
+
+    pub fn region(&self) -> Option<&str> {
+        match self {
+            Resource::Vm(vm) => vm.region.as_deref(),
+            Resource::Volume(volume) => volume.region.as_deref(),
+            Resource::PublicIp(pip) => pip.region.as_deref(),
+            Resource::Snapshot(snapshot) => snapshot.region.as_deref(),
+            Resource::NatServices(nat_services) => nat_services.region.as_deref(),
+            Resource::Aggregate(aggregate) => aggregate.region.as_deref(),
+            Resource::FlexibleGpu(flexible_gpu) => flexible_gpu.region.as_deref(),
+            Resource::LoadBalancer(load_balancer) => load_balancer.region.as_deref(),
+            Resource::Vpn(vpn) => vpn.region.as_deref(),
+            Resource::Oos(oos) => oos.region.as_deref(),
+            Resource::DedicatedInstance(dedicated_instance) => dedicated_instance.region.as_deref(),
+        }
+    }
+
+    pub fn price_per_hour(&self) -> Result<f32, ResourceError> {
+        match self {
+            Resource::Vm(vm) => vm.price_per_hour(),
+            Resource::Volume(volume) => volume.price_per_hour(),
+            Resource::PublicIp(pip) => pip.price_per_hour(),
+            Resource::Snapshot(snapshot) => snapshot.price_per_hour(),
+            Resource::NatServices(nat_services) => nat_services.price_per_hour(),
+            Resource::Aggregate(aggregate) => aggregate.price_per_hour(),
+            Resource::FlexibleGpu(flexible_gpu) => flexible_gpu.price_per_hour(),
+            Resource::LoadBalancer(load_balancer) => load_balancer.price_per_hour(),
+            Resource::Vpn(vpn) => vpn.price_per_hour(),
+            Resource::Oos(oos) => oos.price_per_hour(),
+            Resource::DedicatedInstance(dedicated_instance) => dedicated_instance.price_per_hour(),
+        }
+    }
+
+    pub fn price_per_month(&self) -> Option<f32> {
+        match self {
+            Resource::Vm(vm) => vm.price_per_month,
+            Resource::Volume(volume) => volume.price_per_month,
+            Resource::PublicIp(pip) => pip.price_per_month,
+            Resource::Snapshot(snapshot) => snapshot.price_per_month,
+            Resource::NatServices(nat_services) => nat_services.price_per_month,
+            Resource::Aggregate(aggregate) => aggregate.price_per_month,
+            Resource::FlexibleGpu(flexible_gpu) => flexible_gpu.price_per_month,
+            Resource::LoadBalancer(load_balancer) => load_balancer.price_per_month,
+            Resource::Vpn(vpn) => vpn.price_per_month,
+            Resource::Oos(oos) => oos.price_per_month,
+            Resource::DedicatedInstance(dedicated_instance) => dedicated_instance.price_per_month,
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct Resources {
     pub resources: Vec<Resource>,
@@ -107,41 +244,7 @@ impl Resources {
     pub fn cost_per_hour(&self) -> Result<f32, ResourceError> {
         let mut total = 0f32;
         for resource in &self.resources {
-            match resource {
-                Resource::Volume(volume) => {
-                    total += volume.price_per_hour()?;
-                }
-                Resource::Vm(vm) => {
-                    total += vm.price_per_hour()?;
-                }
-                Resource::PublicIp(pip) => {
-                    total += pip.price_per_hour()?;
-                }
-                Resource::Snapshot(snapshot) => {
-                    total += snapshot.price_per_hour()?;
-                }
-                Resource::NatServices(nat_services) => {
-                    total += nat_services.price_per_hour()?;
-                }
-                Resource::Aggregate(aggregade) => {
-                    total += aggregade.price_per_hour()?;
-                }
-                Resource::FlexibleGpu(flexible_gpu) => {
-                    total += flexible_gpu.price_per_hour()?;
-                }
-                Resource::LoadBalancer(load_balancer) => {
-                    total += load_balancer.price_per_hour()?;
-                }
-                Resource::Vpn(vpn) => {
-                    total += vpn.price_per_hour()?;
-                }
-                Resource::Oos(oos) => {
-                    total += oos.price_per_hour()?;
-                }
-                Resource::DedicatedInstance(dedicated_instance) => {
-                    total += dedicated_instance.price_per_hour()?;
-                }
-            }
+            total += resource.price_per_hour()?;
         }
         Ok(total)
     }
@@ -151,10 +254,15 @@ impl Resources {
     }
 
     pub fn cost_per_year(&self) -> Result<f32, ResourceError> {
-        Ok(self.cost_per_hour()? * HOURS_PER_MONTH * 12.0)
+        Ok(price_per_year(self.cost_per_hour()?))
     }
 }
 
+// Yearly price of an hourly price, totals per resource type use the same definition.
+pub fn price_per_year(price_per_hour: f32) -> f32 {
+    price_per_hour * HOURS_PER_MONTH * 12.0
+}
+
 #[derive(Debug, Clone)]
 pub enum ResourceError {
     NotComputed,
@@ -307,3 +415,33 @@ impl From<Resource> for Aggregate {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn year_price_follows_the_hourly_price() {
+        let mut resources = Resources {
+            resources: vec![Resource::Vm(Vm {
+                vm_vcpu: 2,
+                vm_ram_gb: 4,
+                price_vcpu_per_hour: 0.1,
+                price_ram_gb_per_hour: 0.05,
+                // Dedicated VM, its month price is computed before the factor
+                factor_vm_additional_cost: 1.1,
+                ..Default::default()
+            })],
+        };
+        resources.compute().unwrap();
+        let price_per_hour = resources.cost_per_hour().unwrap();
+        assert!((price_per_hour - 0.44).abs() < 1e-6);
+        assert!((price_per_year(price_per_hour) - 0.44 * 8760.0).abs() < 1e-2);
+        assert_eq!(
+            resources.cost_per_year().unwrap(),
+            price_per_year(price_per_hour)
+        );
+        let price_per_month = resources.resources[0].price_per_month().unwrap();
+        assert!((price_per_year(price_per_hour) - price_per_month * 12.0).abs() > 1.0);
+    }
+}
@@ -0,0 +1,17 @@
+// Currency symbol and ISO 4217 code of the prices of a region.
+fn currency(region: &str) -> (&'static str, &'static str) {
+    match region {
+        "eu-west-2" | "cloudgouv-eu-west-1" => ("€", "EUR"),
+        "ap-northeast-1" => ("¥", "JPY"),
+        "us-east-2" | "us-west-1" => ("$", "USD"),
+        _ => ("€", "EUR"),
+    }
+}
+
+pub fn get_currency(region: &str) -> String {
+    String::from(currency(region).0)
+}
+
+pub fn get_currency_code(region: &str) -> &'static str {
+    currency(region).1
+}
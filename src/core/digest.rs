@@ -1,15 +1,27 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-use chrono::NaiveDate;
+use chrono::{Datelike, Months, NaiveDate};
 use log::warn;
 use serde::{Deserialize, Serialize};
 
 use super::{Resource, Resources};
 use std::error::Error;
 
+// Category of the consumption entries that cannot be mapped to a resource or priced
+pub const UNMAPPED: &str = "Unmapped";
+
 #[derive(Clone, Debug, Default)]
 pub struct Digest {
     pub price: Option<f32>,
+    // Entries of the Unmapped category
+    pub entries: Vec<UnmappedEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UnmappedEntry {
+    pub entry_id: String,
+    pub value: f64,
+    pub price: f32,
 }
 
 pub fn match_entry_id_resource_type(entry_id: &String) -> Option<String> {
@@ -19,23 +31,27 @@ pub fn match_entry_id_resource_type(entry_id: &String) -> Option<String> {
         s if s.starts_with("TinaOS-FCU/CustomCore") => Some(String::from("Vm")),
         s if s.starts_with("TinaOS-FCU/CustomRam") => Some(String::from("Vm")),
         s if s.starts_with("TinaOS-FCU/ProductUsage") => Some(String::from("Vm")),
+        // TinaOS-FCU/DedicatedInstanceSurplus/RunInstances
+        s if s.starts_with("TinaOS-FCU/DedicatedInstanceSurplus") => Some(String::from("Vm")),
+        // TinaOS-FCU/UseDedicated/RunDedicatedInstances
+        s if s.starts_with("TinaOS-FCU/UseDedicated") => Some(String::from("DedicatedInstance")),
+        // TinaOS-FCU/Gpu:attach:<model>/AllocateGpu and Gpu:allocate:<model>/AllocateGpu
+        s if s.starts_with("TinaOS-FCU/Gpu:") => Some(String::from("FlexibleGpu")),
         // Volume
         s if s.starts_with("TinaOS-FCU/BSU") => Some(String::from("Volume")),
         // VPN
-        s if s == "TinaOS-FCU/ConnectionUsage/CreateVpnConnection" => Some(String::from("Vpn")),
+        s if s.starts_with("TinaOS-FCU/ConnectionUsage") => Some(String::from("Vpn")),
         // Public IP
         s if s.starts_with("TinaOS-FCU/ElasticIP") => Some(String::from("PublicIp")),
         // NAT Services
-        s if s == "TinaOS-FCU/NatGatewayUsage/CreateNatGateway" => {
-            Some(String::from("NatServices"))
-        }
+        s if s.starts_with("TinaOS-FCU/NatGateway") => Some(String::from("NatServices")),
         // Snapshots
-        s if s == "TinaOS-FCU/Snapshot:Usage/Snapshot" => Some(String::from("Snapshot")),
+        s if s.starts_with("TinaOS-FCU/Snapshot") => Some(String::from("Snapshot")),
         // OOS
         s if s.starts_with("TinaOS-OOS") => Some(String::from("Oos")),
         s if s.starts_with("TinaOS-OSU") => Some(String::from("Oos")),
         // LBU
-        s if s == "TinaOS-LBU/LBU:Usage/CreateLoadBalancer" => Some(String::from("LoadBalancer")),
+        s if s.starts_with("TinaOS-LBU") => Some(String::from("LoadBalancer")),
         _ => {
             warn!("Entryid {} does not match any resources", entry_id);
             None
@@ -43,6 +59,25 @@ pub fn match_entry_id_resource_type(entry_id: &String) -> Option<String> {
     }
 }
 
+// Pricing component of a VM consumption entry.
+pub fn match_entry_id_vm_component(entry_id: &str) -> Option<String> {
+    match entry_id {
+        // TinaOS-FCU/ProductUsage:<vm type>/RunInstances-<product code>-OD
+        s if s.starts_with("TinaOS-FCU/ProductUsage") => s
+            .rsplit('/')
+            .next()
+            .and_then(|operation| operation.split('-').nth(1))
+            .map(|product_code| format!("Vm/License:{product_code}")),
+        s if s.starts_with("TinaOS-FCU/BoxUsage") => Some(String::from("Vm/Box")),
+        s if s.starts_with("TinaOS-FCU/CustomCore") => Some(String::from("Vm/Core")),
+        s if s.starts_with("TinaOS-FCU/CustomRam") => Some(String::from("Vm/Ram")),
+        s if s.starts_with("TinaOS-FCU/DedicatedInstanceSurplus") => {
+            Some(String::from("Vm/DedicatedSurcharge"))
+        }
+        _ => None,
+    }
+}
+
 pub struct Drifts {
     pub drifts: Vec<Drift>,
 }
@@ -53,10 +88,83 @@ pub struct Drift {
     pub osc_cost_price: f32,
     pub digest_price: f32,
     pub drift: i32,
+    // Consumption entries behind the Unmapped category
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<UnmappedEntry>,
 }
 
-pub fn compute_drift(
+// Compare the hourly price of each category or pricing component over the period with
+// the consumption billed for it.
+pub fn compute_component_drift(
     digest: HashMap<String, Digest>,
+    estimates: HashMap<String, f32>,
+    from_date: &str,
+    to_date: &str,
+) -> Result<Drifts, Box<dyn Error>> {
+    let from_date = NaiveDate::parse_from_str(from_date, "%Y-%m-%d")?;
+    let to_date = NaiveDate::parse_from_str(to_date, "%Y-%m-%d")?;
+    let diff = (to_date - from_date).num_hours() as f32;
+
+    let categories = digest
+        .keys()
+        .chain(estimates.keys())
+        .collect::<BTreeSet<&String>>();
+    let mut drifts = Vec::<Drift>::new();
+    for category in categories {
+        let osc_cost_price = estimates.get(category).copied().unwrap_or(0.0) * diff;
+        let (digest_price, entries) = match digest.get(category) {
+            Some(Digest {
+                price: Some(price),
+                entries,
+            }) => (*price, entries.clone()),
+            Some(_) => {
+                warn!("the digest price for this component {category} has not been computed");
+                continue;
+            }
+            None => (0.0, Vec::new()),
+        };
+        let drift = match (osc_cost_price == 0.0, digest_price == 0.0) {
+            (true, true) => continue,
+            (false, true) => 100,
+            (_, false) => ((osc_cost_price - digest_price) * 100.0 / digest_price) as i32,
+        };
+
+        drifts.push(Drift {
+            category: category.clone(),
+            osc_cost_price,
+            digest_price,
+            drift,
+            entries,
+        })
+    }
+
+    Ok(Drifts { drifts })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AccountDrift {
+    pub account_id: String,
+    #[serde(flatten)]
+    pub drift: Drift,
+}
+
+pub struct AccountDrifts {
+    pub drifts: Vec<AccountDrift>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DailyDrift {
+    pub date: String,
+    #[serde(flatten)]
+    pub drift: Drift,
+}
+
+pub struct DailyDrifts {
+    pub drifts: Vec<DailyDrift>,
+}
+
+pub fn compute_drift(
+    mut digest: HashMap<String, Digest>,
     resources: &Resources,
     from_date: &str,
     to_date: &str,
@@ -70,7 +178,8 @@ pub fn compute_drift(
     for resource in resources.resources.iter() {
         match resource {
             Resource::Aggregate(osc_cost) => {
-                let (digest_price, drift) = match digest.get(&osc_cost.aggregated_resource_type) {
+                let (digest_price, drift) = match digest.remove(&osc_cost.aggregated_resource_type)
+                {
                     Some(digest) => {
                         let Some(price) = digest.price else {
                             warn!(
@@ -109,6 +218,7 @@ pub fn compute_drift(
                     osc_cost_price: osc_cost.price_per_hour.unwrap_or(0.0) * diff,
                     digest_price,
                     drift,
+                    entries: Vec::new(),
                 })
             }
             _ => {
@@ -118,5 +228,422 @@ pub fn compute_drift(
         }
     }
 
+    // Billed categories without resource in the inventory, including the unmapped entries
+    let mut billed_only = digest.into_iter().collect::<Vec<(String, Digest)>>();
+    billed_only.sort_by(|a, b| a.0.cmp(&b.0));
+    for (category, digest) in billed_only {
+        let Some(digest_price) = digest.price else {
+            warn!("the digest price for this resource {category} has not been computed");
+            continue;
+        };
+        if digest_price == 0.0 && digest.entries.is_empty() {
+            continue;
+        }
+        drifts.push(Drift {
+            category,
+            osc_cost_price: 0.0,
+            digest_price,
+            drift: -100,
+            entries: digest.entries,
+        });
+    }
+
     Ok(Drifts { drifts })
 }
+
+// Resource id (none for consumption billed without id) and category.
+pub type ResourceKey = (Option<String>, String);
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResourceDrift {
+    pub resource_id: Option<String>,
+    pub category: String,
+    // matched, billed_only (e.g. deleted during the period) or inventory_only
+    pub status: String,
+    pub osc_cost_price: f32,
+    pub digest_price: f32,
+    pub drift: i32,
+}
+
+pub struct ResourceDrifts {
+    pub drifts: Vec<ResourceDrift>,
+}
+
+// Compare the price of each non aggregated resource over the period with the consumption
+// billed for its id, largest differences first.
+pub fn compute_resource_drift(
+    mut digest: HashMap<ResourceKey, f32>,
+    resources: &Resources,
+    from_date: &str,
+    to_date: &str,
+) -> Result<ResourceDrifts, Box<dyn Error>> {
+    let from_date = NaiveDate::parse_from_str(from_date, "%Y-%m-%d")?;
+    let to_date = NaiveDate::parse_from_str(to_date, "%Y-%m-%d")?;
+    let diff = (to_date - from_date).num_hours() as f32;
+
+    let mut drifts = Vec::<ResourceDrift>::new();
+    for resource in resources.resources.iter() {
+        if let Resource::Aggregate(_) = resource {
+            warn!("cannot handle aggregated resources");
+            continue;
+        }
+        let osc_cost_price = resource.price_per_hour()? * diff;
+        let key = (
+            resource.resource_id().map(String::from),
+            resource.resource_type().to_string(),
+        );
+        let (status, digest_price, drift) = match digest.remove(&key) {
+            Some(price) if price != 0.0 => (
+                "matched",
+                price,
+                ((osc_cost_price - price) * 100.0 / price) as i32,
+            ),
+            Some(price) if osc_cost_price == 0.0 => ("matched", price, 0),
+            // Free resources are not expected in the consumption
+            None if osc_cost_price == 0.0 => continue,
+            _ => ("inventory_only", 0.0, 100),
+        };
+        drifts.push(ResourceDrift {
+            resource_id: key.0,
+            category: key.1,
+            status: String::from(status),
+            osc_cost_price,
+            digest_price,
+            drift,
+        });
+    }
+    // What is left has been billed without being in the inventory
+    for ((resource_id, category), digest_price) in digest {
+        drifts.push(ResourceDrift {
+            resource_id,
+            category,
+            status: String::from("billed_only"),
+            osc_cost_price: 0.0,
+            digest_price,
+            drift: -100,
+        });
+    }
+
+    drifts.sort_by(|a, b| {
+        (b.osc_cost_price - b.digest_price)
+            .abs()
+            .total_cmp(&(a.osc_cost_price - a.digest_price).abs())
+            .then_with(|| a.category.cmp(&b.category))
+            .then_with(|| a.resource_id.cmp(&b.resource_id))
+    });
+    Ok(ResourceDrifts { drifts })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MonthToDate {
+    // Actual spend covers [from_date, to_date), the projection [to_date, end_date)
+    pub from_date: String,
+    pub to_date: String,
+    pub end_date: String,
+    pub categories: Vec<MonthToDateCategory>,
+    pub actual: f32,
+    pub projected: f32,
+    pub total: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MonthToDateCategory {
+    pub category: String,
+    pub actual: f32,
+    pub projected: f32,
+    pub total: f32,
+}
+
+// First day of the month, today and first day of the next month.
+pub fn month_to_date_period(today: NaiveDate) -> (NaiveDate, NaiveDate, NaiveDate) {
+    let from_date = today.with_day(1).unwrap_or(today);
+    let end_date = from_date + Months::new(1);
+    (from_date, today, end_date)
+}
+
+// Add the projection of the aggregated resources until the end of the month to the
+// consumption billed since its beginning.
+pub fn compute_month_to_date(
+    digest: HashMap<String, Digest>,
+    resources: &Resources,
+    today: NaiveDate,
+) -> Result<MonthToDate, Box<dyn Error>> {
+    let (from_date, to_date, end_date) = month_to_date_period(today);
+    let remaining_hours = (end_date - to_date).num_hours() as f32;
+
+    let mut categories = BTreeMap::<String, MonthToDateCategory>::new();
+    for (category, digest) in digest {
+        let Some(price) = digest.price else {
+            warn!("the digest price for this resource {category} has not been computed");
+            continue;
+        };
+        categories.insert(
+            category.clone(),
+            MonthToDateCategory {
+                category,
+                actual: price,
+                projected: 0.0,
+                total: 0.0,
+            },
+        );
+    }
+    for resource in resources.resources.iter() {
+        let Resource::Aggregate(aggregate) = resource else {
+            warn!("cannot handle non aggreagated resources");
+            continue;
+        };
+        let category = aggregate.aggregated_resource_type.clone();
+        categories
+            .entry(category.clone())
+            .or_insert(MonthToDateCategory {
+                category,
+                actual: 0.0,
+                projected: 0.0,
+                total: 0.0,
+            })
+            .projected += aggregate.price_per_hour.unwrap_or_default() * remaining_hours;
+    }
+
+    let mut month_to_date = MonthToDate {
+        from_date: from_date.to_string(),
+        to_date: to_date.to_string(),
+        end_date: end_date.to_string(),
+        categories: Vec::new(),
+        actual: 0.0,
+        projected: 0.0,
+        total: 0.0,
+    };
+    for mut category in categories.into_values() {
+        category.total = category.actual + category.projected;
+        month_to_date.actual += category.actual;
+        month_to_date.projected += category.projected;
+        month_to_date.total += category.total;
+        month_to_date.categories.push(category);
+    }
+    Ok(month_to_date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Aggregate;
+
+    // Catalog ids (service/type/operation) priced by the inventory, the consumption
+    // entries use the same ids
+    #[test]
+    fn entry_ids_match_resource_types() {
+        for (entry_id, resource_type) in [
+            ("TinaOS-FCU/BoxUsage:tinav5.c2r4p2/RunInstances-OD", "Vm"),
+            ("TinaOS-FCU/CustomCore:v5-p2/RunInstances-OD", "Vm"),
+            ("TinaOS-FCU/CustomRam/RunInstances-OD", "Vm"),
+            ("TinaOS-FCU/ProductUsage/RunInstances-0002-OD", "Vm"),
+            ("TinaOS-FCU/DedicatedInstanceSurplus/RunInstances", "Vm"),
+            (
+                "TinaOS-FCU/UseDedicated/RunDedicatedInstances",
+                "DedicatedInstance",
+            ),
+            (
+                "TinaOS-FCU/Gpu:attach:nvidia-p100/AllocateGpu",
+                "FlexibleGpu",
+            ),
+            (
+                "TinaOS-FCU/Gpu:allocate:nvidia-p100/AllocateGpu",
+                "FlexibleGpu",
+            ),
+            ("TinaOS-FCU/BSU:VolumeUsage:gp2/CreateVolume", "Volume"),
+            ("TinaOS-FCU/BSU:VolumeIOPS:io1/CreateVolume", "Volume"),
+            ("TinaOS-FCU/ConnectionUsage/CreateVpnConnection", "Vpn"),
+            (
+                "TinaOS-FCU/ElasticIP:IdleAddress/AssociateAddressVPC",
+                "PublicIp",
+            ),
+            ("TinaOS-FCU/NatGatewayUsage/CreateNatGateway", "NatServices"),
+            ("TinaOS-FCU/Snapshot:Usage/Snapshot", "Snapshot"),
+            ("TinaOS-OOS/enterprise/OOSStorage", "Oos"),
+            ("TinaOS-LBU/LBU:Usage/CreateLoadBalancer", "LoadBalancer"),
+        ] {
+            assert_eq!(
+                match_entry_id_resource_type(&entry_id.to_string()).as_deref(),
+                Some(resource_type),
+                "{entry_id}"
+            );
+        }
+        assert_eq!(
+            match_entry_id_resource_type(&String::from("TinaOS-DirectLink/Usage/Create")),
+            None
+        );
+    }
+
+    #[test]
+    fn entry_ids_match_vm_components() {
+        for (entry_id, component) in [
+            (
+                "TinaOS-FCU/BoxUsage:m4.large/RunInstances-OD",
+                Some("Vm/Box"),
+            ),
+            (
+                "TinaOS-FCU/CustomCore:v5-p2/RunInstances-OD",
+                Some("Vm/Core"),
+            ),
+            ("TinaOS-FCU/CustomRam/RunInstances-OD", Some("Vm/Ram")),
+            (
+                "TinaOS-FCU/ProductUsage:tinav5.c2r4p2/RunInstances-0002-OD",
+                Some("Vm/License:0002"),
+            ),
+            (
+                "TinaOS-FCU/DedicatedInstanceSurplus/RunInstances",
+                Some("Vm/DedicatedSurcharge"),
+            ),
+            ("TinaOS-FCU/BSU:VolumeUsage:gp2/CreateVolume", None),
+        ] {
+            assert_eq!(
+                match_entry_id_vm_component(entry_id).as_deref(),
+                component,
+                "{entry_id}"
+            );
+        }
+    }
+
+    #[test]
+    fn month_to_date_period_ends_next_month() {
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(
+            month_to_date_period(day(2024, 2, 15)),
+            (day(2024, 2, 1), day(2024, 2, 15), day(2024, 3, 1))
+        );
+        assert_eq!(
+            month_to_date_period(day(2024, 12, 31)),
+            (day(2024, 12, 1), day(2024, 12, 31), day(2025, 1, 1))
+        );
+        assert_eq!(
+            month_to_date_period(day(2024, 1, 1)),
+            (day(2024, 1, 1), day(2024, 1, 1), day(2024, 2, 1))
+        );
+    }
+
+    #[test]
+    fn month_to_date_adds_the_projection() {
+        use crate::core::volumes::Volume;
+
+        let aggregate = |resource_type: &str, price_per_hour| {
+            Resource::Aggregate(Aggregate {
+                price_per_hour: Some(price_per_hour),
+                aggregated_resource_type: resource_type.to_string(),
+                count: 1,
+                ..Default::default()
+            })
+        };
+        let digest = HashMap::from([
+            (
+                String::from("Vm"),
+                Digest {
+                    price: Some(100.0),
+                    ..Default::default()
+                },
+            ),
+            (
+                String::from(UNMAPPED),
+                Digest {
+                    price: Some(5.0),
+                    ..Default::default()
+                },
+            ),
+            (String::from("Snapshot"), Digest::default()),
+        ]);
+        let resources = Resources {
+            resources: vec![
+                aggregate("Vm", 1.0),
+                aggregate("Volume", 0.5),
+                // Only aggregated resources are projected
+                Resource::Volume(Volume {
+                    price_per_hour: Some(10.0),
+                    ..Default::default()
+                }),
+            ],
+        };
+        let today = NaiveDate::from_ymd_opt(2024, 4, 21).unwrap();
+        let month_to_date = compute_month_to_date(digest, &resources, today).unwrap();
+
+        assert_eq!(month_to_date.from_date, "2024-04-01");
+        assert_eq!(month_to_date.to_date, "2024-04-21");
+        assert_eq!(month_to_date.end_date, "2024-05-01");
+        // 10 days left in April
+        let categories = month_to_date
+            .categories
+            .iter()
+            .map(|c| (c.category.as_str(), c.actual, c.projected, c.total))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            categories,
+            vec![
+                (UNMAPPED, 5.0, 0.0, 5.0),
+                ("Vm", 100.0, 240.0, 340.0),
+                ("Volume", 0.0, 120.0, 120.0),
+            ]
+        );
+        assert_eq!(month_to_date.actual, 105.0);
+        assert_eq!(month_to_date.projected, 360.0);
+        assert_eq!(month_to_date.total, 465.0);
+    }
+
+    #[test]
+    fn resource_drift_statuses() {
+        use crate::core::{public_ips::PublicIp, volumes::Volume};
+
+        let volume = |resource_id: &str, price_per_hour| {
+            Resource::Volume(Volume {
+                resource_id: Some(resource_id.to_string()),
+                price_per_hour: Some(price_per_hour),
+                ..Default::default()
+            })
+        };
+        let key = |resource_id: &str, category: &str| {
+            (Some(resource_id.to_string()), category.to_string())
+        };
+        let digest = HashMap::from([
+            (key("vol-matched", "Volume"), 30.0),
+            (key("vol-free", "Volume"), 0.0),
+            (key("vol-deleted", "Volume"), 10.0),
+            // Same id, another resource type
+            (key("vol-inventory", "Snapshot"), 1.0),
+        ]);
+        let resources = Resources {
+            resources: vec![
+                volume("vol-matched", 1.0),
+                volume("vol-free", 0.0),
+                volume("vol-inventory", 0.5),
+                volume("vol-unbilled-free", 0.0),
+                Resource::PublicIp(PublicIp {
+                    price_per_hour: Some(1.0),
+                    ..Default::default()
+                }),
+                Resource::Aggregate(Aggregate::default()),
+            ],
+        };
+        let drifts =
+            compute_resource_drift(digest, &resources, "2024-04-01", "2024-04-02").unwrap();
+        let drifts = drifts
+            .drifts
+            .iter()
+            .map(|d| {
+                (
+                    d.resource_id.as_deref().unwrap_or_default(),
+                    d.category.as_str(),
+                    d.status.as_str(),
+                    d.drift,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            drifts,
+            vec![
+                ("", "PublicIp", "inventory_only", 100),
+                ("vol-inventory", "Volume", "inventory_only", 100),
+                ("vol-deleted", "Volume", "billed_only", -100),
+                ("vol-matched", "Volume", "matched", -20),
+                ("vol-inventory", "Snapshot", "billed_only", -100),
+                ("vol-free", "Volume", "matched", 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use super::{Resource, Resources, Tags};
+use std::error::Error;
+
+pub const ESTIMATE: &str = "Estimate";
+pub const CONSUMPTION: &str = "Consumption";
+
+// One row of a FinOps FOCUS export, estimated from the inventory or from the consumption.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Charge {
+    pub source: String,
+    pub billing_account_id: Option<String>,
+    pub sub_account_id: Option<String>,
+    pub region_id: Option<String>,
+    pub resource_id: Option<String>,
+    pub resource_type: Option<String>,
+    pub service_name: String,
+    pub service_category: String,
+    // Catalog entry of the consumption
+    pub sku_id: Option<String>,
+    pub charge_description: Option<String>,
+    pub charge_period_start: String,
+    pub charge_period_end: String,
+    pub list_unit_price: Option<f32>,
+    pub pricing_quantity: f32,
+    pub pricing_unit: Option<String>,
+    pub list_cost: f32,
+    pub effective_cost: f32,
+    // Nothing is billed for the estimated charges
+    pub billed_cost: f32,
+    pub tags: Option<Tags>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct Charges {
+    pub charges: Vec<Charge>,
+}
+
+// Price of each resource over the period, from its hourly price.
+pub fn estimate_charges(
+    resources: &Resources,
+    from_date: &str,
+    to_date: &str,
+) -> Result<Vec<Charge>, Box<dyn Error>> {
+    let (charge_period_start, charge_period_end, hours) = charge_period(from_date, to_date)?;
+
+    let mut charges = Vec::new();
+    for resource in &resources.resources {
+        let price_per_hour = resource.price_per_hour()?;
+        let resource_type = resource.resource_type();
+        let (service_name, service_category) = service(resource_type);
+        let cost = price_per_hour * hours;
+        charges.push(Charge {
+            source: ESTIMATE.to_string(),
+            billing_account_id: resource.account_id().map(String::from),
+            sub_account_id: resource.account_id().map(String::from),
+            region_id: resource.region().map(String::from),
+            resource_id: resource.resource_id().map(String::from),
+            resource_type: Some(resource_type.to_string()),
+            service_name: service_name.to_string(),
+            service_category: service_category.to_string(),
+            sku_id: None,
+            charge_description: Some(match resource {
+                Resource::Aggregate(aggregate) => {
+                    format!("{} {resource_type} resources", aggregate.count)
+                }
+                _ => format!("{resource_type} estimated from the inventory"),
+            }),
+            charge_period_start: charge_period_start.clone(),
+            charge_period_end: charge_period_end.clone(),
+            list_unit_price: Some(price_per_hour),
+            pricing_quantity: hours,
+            pricing_unit: Some(String::from("Hours")),
+            list_cost: cost,
+            effective_cost: cost,
+            billed_cost: 0.0,
+            tags: resource.tags().cloned(),
+        });
+    }
+    Ok(charges)
+}
+
+// RFC3339 bounds of the period and its number of hours.
+pub fn charge_period(
+    from_date: &str,
+    to_date: &str,
+) -> Result<(String, String, f32), Box<dyn Error>> {
+    let from_date = NaiveDate::parse_from_str(from_date, "%Y-%m-%d")?;
+    let to_date = NaiveDate::parse_from_str(to_date, "%Y-%m-%d")?;
+    let hours = (to_date - from_date).num_hours() as f32;
+    Ok((
+        format!("{from_date}T00:00:00Z"),
+        format!("{to_date}T00:00:00Z"),
+        hours,
+    ))
+}
+
+// FOCUS service name and category of a resource type, the service names are the ones of
+// the consumption entries.
+pub fn service(resource_type: &str) -> (&'static str, &'static str) {
+    match resource_type {
+        "Vm" | "FlexibleGpu" | "DedicatedInstance" => ("TinaOS-FCU", "Compute"),
+        "Volume" | "Snapshot" => ("TinaOS-FCU", "Storage"),
+        "PublicIp" | "NatServices" | "Vpn" => ("TinaOS-FCU", "Networking"),
+        "LoadBalancer" => ("TinaOS-LBU", "Networking"),
+        "Oos" => ("TinaOS-OOS", "Storage"),
+        _ => ("", "Other"),
+    }
+}
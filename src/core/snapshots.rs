@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use super::{ResourceError, ResourceTrait, HOURS_PER_MONTH};
+use super::{ResourceError, ResourceTrait, Tags, HOURS_PER_MONTH};
 
 use crate::VERSION;
 
@@ -11,6 +11,8 @@ pub struct Snapshot {
     pub read_date_rfc3339: Option<String>,
     pub region: Option<String>,
     pub resource_id: Option<String>,
+    // Resource tags, by key
+    pub tags: Option<Tags>,
     pub price_per_hour: Option<f32>,
     pub price_per_month: Option<f32>,
     pub volume_size_gib: Option<i32>,
@@ -44,6 +46,7 @@ impl Default for Snapshot {
             read_date_rfc3339: Some("".to_string()),
             region: Some("".to_string()),
             resource_id: None,
+            tags: None,
             price_per_hour: Some(0.0),
             price_per_month: Some(0.0),
             volume_size_gib: Some(0),
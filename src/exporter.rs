@@ -0,0 +1,7 @@
+pub mod api;
+pub mod auth;
+pub mod dashboard;
+pub mod drift;
+pub mod history;
+pub mod push;
+pub mod tls;
@@ -0,0 +1,213 @@
+use std::str::FromStr;
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use http::StatusCode;
+use osc_cost::{
+    core::{price_per_year, Resource, Resources},
+    oapi::Filter,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{fetch_resources, AppState};
+
+type ApiError = (StatusCode, String);
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ResourcesQuery {
+    pub resource_type: Option<String>,
+    pub resource_id: Option<String>,
+    // KEY=VALUE, same as --filter-tag on the CLI
+    pub tag: Option<String>,
+    pub tag_key: Option<String>,
+    pub tag_value: Option<String>,
+}
+
+impl ResourcesQuery {
+    fn validate(&self) -> Result<(), ApiError> {
+        if let Some(resource_type) = &self.resource_type {
+            // Aggregates are not a resource type of the inventory
+            if matches!(
+                Resource::from_str(resource_type),
+                Err(_) | Ok(Resource::Aggregate(_))
+            ) {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("unknown resource type {resource_type}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Tag filters are forwarded to the API calls, like the CLI does
+    fn filter(&self) -> Option<Filter> {
+        if self.tag.is_none() && self.tag_key.is_none() && self.tag_value.is_none() {
+            return None;
+        }
+        Some(Filter {
+            tag_keys: self.tag_key.iter().cloned().collect(),
+            tag_values: self.tag_value.iter().cloned().collect(),
+            tags: self.tag.iter().cloned().collect(),
+            skip_resource: Vec::new(),
+        })
+    }
+
+    fn matches(&self, resource: &Resource) -> bool {
+        if let Some(resource_type) = &self.resource_type {
+            if resource.resource_type() != resource_type {
+                return false;
+            }
+        }
+        if let Some(resource_id) = &self.resource_id {
+            if resource.resource_id() != Some(resource_id.as_str()) {
+                return false;
+            }
+        }
+        true
+    }
+
+    async fn fetch(&self, state: &AppState) -> Result<Resources, ApiError> {
+        self.validate()?;
+        let mut resources = fetch_resources(state, self.filter())
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        resources
+            .resources
+            .retain(|resource| self.matches(resource));
+        Ok(resources)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub account_id: Option<String>,
+    pub region: Option<String>,
+    pub price_per_hour: f32,
+    pub price_per_month: f32,
+    pub price_per_year: f32,
+    pub resource_types: Vec<ResourceTypeSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResourceTypeSummary {
+    pub resource_type: String,
+    pub count: i32,
+    pub price_per_hour: f32,
+    pub price_per_month: f32,
+    pub price_per_year: f32,
+}
+
+impl TryFrom<Resources> for Summary {
+    type Error = String;
+
+    fn try_from(resources: Resources) -> Result<Self, Self::Error> {
+        let resources = resources.aggregate();
+        let mut summary = Summary {
+            account_id: None,
+            region: None,
+            price_per_hour: resources.cost_per_hour().map_err(|e| e.to_string())?,
+            price_per_month: resources.cost_per_month().map_err(|e| e.to_string())?,
+            price_per_year: resources.cost_per_year().map_err(|e| e.to_string())?,
+            resource_types: Vec::new(),
+        };
+        for resource in resources.resources {
+            let Resource::Aggregate(agg) = resource else {
+                continue;
+            };
+            if summary.account_id.is_none() {
+                summary.account_id = agg.account_id;
+            }
+            if summary.region.is_none() {
+                summary.region = agg.region;
+            }
+            let price_per_hour = agg.price_per_hour.unwrap_or_default();
+            summary.resource_types.push(ResourceTypeSummary {
+                resource_type: agg.aggregated_resource_type,
+                count: agg.count,
+                price_per_hour,
+                price_per_month: agg.price_per_month.unwrap_or_default(),
+                price_per_year: price_per_year(price_per_hour),
+            });
+        }
+        summary
+            .resource_types
+            .sort_by(|a, b| a.resource_type.cmp(&b.resource_type));
+        Ok(summary)
+    }
+}
+
+pub async fn resources(
+    State(state): State<AppState>,
+    Query(query): Query<ResourcesQuery>,
+) -> Result<Json<Vec<Resource>>, ApiError> {
+    let resources = query.fetch(&state).await?;
+    Ok(Json(resources.resources))
+}
+
+pub async fn summary(
+    State(state): State<AppState>,
+    Query(query): Query<ResourcesQuery>,
+) -> Result<Json<Summary>, ApiError> {
+    let resources = query.fetch(&state).await?;
+    let summary =
+        Summary::try_from(resources).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(resource_type: &str) -> ResourcesQuery {
+        ResourcesQuery {
+            resource_type: Some(resource_type.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validate_resource_types() {
+        assert!(query("Vm").validate().is_ok());
+        assert!(query("LoadBalancer").validate().is_ok());
+        assert!(query("Aggregate").validate().is_err());
+        assert!(query("Unknown").validate().is_err());
+    }
+
+    #[test]
+    fn summary_year_prices_follow_the_hourly_prices() {
+        use osc_cost::core::{vms::Vm, volumes::Volume};
+
+        let mut resources = Resources {
+            resources: vec![
+                Resource::Vm(Vm {
+                    vm_vcpu: 2,
+                    vm_ram_gb: 4,
+                    price_vcpu_per_hour: 0.1,
+                    price_ram_gb_per_hour: 0.05,
+                    // Dedicated VM, its month price is computed before the factor
+                    factor_vm_additional_cost: 1.1,
+                    ..Default::default()
+                }),
+                Resource::Volume(Volume {
+                    price_per_hour: Some(0.5),
+                    ..Default::default()
+                }),
+            ],
+        };
+        resources.compute().unwrap();
+        let summary = Summary::try_from(resources).unwrap();
+        let vm = &summary.resource_types[0];
+        assert_eq!(vm.resource_type, "Vm");
+        assert_eq!(vm.price_per_year, price_per_year(vm.price_per_hour));
+        assert!((vm.price_per_year - vm.price_per_month * 12.0).abs() > 1.0);
+        let years = summary
+            .resource_types
+            .iter()
+            .map(|resource_type| resource_type.price_per_year)
+            .sum::<f32>();
+        assert!((years - summary.price_per_year).abs() < 1e-2);
+    }
+}
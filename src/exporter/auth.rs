@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{header, StatusCode};
+
+#[derive(Debug, Default)]
+pub struct Auth {
+    pub token: Option<String>,
+    pub basic: Option<(String, String)>,
+}
+
+impl Auth {
+    pub fn new(token: Option<String>, basic: Option<String>) -> Result<Self, String> {
+        let basic = match basic {
+            Some(credentials) => match credentials.split_once(':') {
+                Some((user, password)) => Some((user.to_string(), password.to_string())),
+                None => return Err("basic auth credentials must be USER:PASSWORD".to_string()),
+            },
+            None => None,
+        };
+        Ok(Auth { token, basic })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.token.is_some() || self.basic.is_some()
+    }
+
+    fn is_authorized(&self, authorization: &str) -> bool {
+        if let (Some(token), Some(value)) = (&self.token, authorization.strip_prefix("Bearer ")) {
+            return constant_time_eq(token.as_bytes(), value.trim().as_bytes());
+        }
+        if let (Some((user, password)), Some(value)) =
+            (&self.basic, authorization.strip_prefix("Basic "))
+        {
+            let Ok(decoded) = STANDARD.decode(value.trim()) else {
+                return false;
+            };
+            let expected = format!("{user}:{password}");
+            return constant_time_eq(expected.as_bytes(), &decoded);
+        }
+        false
+    }
+}
+
+// Compare secrets without leaking the position of the first difference.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub async fn require_auth(State(auth): State<Arc<Auth>>, request: Request, next: Next) -> Response {
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| auth.is_authorized(value));
+    if authorized {
+        return next.run(request).await;
+    }
+
+    let challenge = match auth.basic {
+        Some(_) => "Basic realm=\"osc-cost\"",
+        None => "Bearer realm=\"osc-cost\"",
+    };
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, challenge)],
+        "Unauthorized",
+    )
+        .into_response()
+}
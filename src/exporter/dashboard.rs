@@ -0,0 +1,41 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap},
+    response::{Html, IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::{
+    fetch_resources, metrics,
+    output::html::{html, Options},
+    AppState,
+};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DashboardQuery {
+    pub top: Option<usize>,
+}
+
+// Browsers get the dashboard, scrapers and other clients keep getting the metrics.
+pub async fn dashboard(
+    State(state): State<AppState>,
+    Query(query): Query<DashboardQuery>,
+    headers: HeaderMap,
+) -> Result<Response, String> {
+    let accept_html = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    if !accept_html {
+        return metrics(State(state)).await;
+    }
+
+    let resources = fetch_resources(&state, None).await?;
+    let options = Options {
+        title: "osc-cost",
+        refresh: Some(state.dashboard_refresh),
+        top: Some(query.top.unwrap_or(state.dashboard_top)),
+    };
+    let html = html(&resources, &options).map_err(|e| e.to_string())?;
+    Ok(Html(html).into_response())
+}
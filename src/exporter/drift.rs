@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use chrono::{Days, Utc};
+use log::{error, info};
+use osc_cost::core::digest::Drifts;
+
+use crate::{fetch_resources, AppState};
+
+// Periodically compare the current inventory with the consumption of the last days.
+pub async fn drift_loop(state: AppState, days: u64, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        match compute(&state, days).await {
+            Ok(drifts) => match state.drifts.lock() {
+                Ok(mut last) => {
+                    info!("computed drift for {} categories", drifts.drifts.len());
+                    *last = Some(drifts);
+                }
+                Err(e) => error!("could not lock drifts: {e}"),
+            },
+            Err(e) => error!("could not compute drift: {e}"),
+        }
+    }
+}
+
+async fn compute(state: &AppState, days: u64) -> Result<Drifts, String> {
+    let resources = fetch_resources(state, None).await?.aggregate();
+    let to_date = Utc::now().date_naive();
+    let from_date = to_date - Days::new(days);
+
+    let local_lock = state.input.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut inputs = local_lock
+            .lock()
+            .map_err(|e| format!("Could not lock mutex: {e}"))?;
+        inputs
+            .drift(
+                &resources,
+                &from_date.format("%Y-%m-%d").to_string(),
+                &to_date.format("%Y-%m-%d").to_string(),
+            )
+            .map_err(|e| format!("Could not compute drift: {e}"))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {e}"))?
+}
@@ -0,0 +1,92 @@
+use std::sync::Mutex;
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use http::StatusCode;
+use log::error;
+use osc_cost::{
+    anomaly::{detect_from_history, Anomalies, AnomalyConfig},
+    core::Resources,
+    history::{History, HistoryEntry, HistoryQuery},
+};
+
+use crate::AppState;
+
+// Records the inventory at most once per interval, whatever the scrape period.
+pub struct Recorder {
+    history: History,
+    interval: TimeDelta,
+    last_record: Option<DateTime<Utc>>,
+}
+
+impl Recorder {
+    pub fn new(history: History, interval_secs: u64) -> Self {
+        Recorder {
+            history,
+            interval: TimeDelta::seconds(interval_secs as i64),
+            last_record: None,
+        }
+    }
+
+    fn record(&mut self, resources: &Resources) {
+        let now = Utc::now();
+        if let Some(last_record) = self.last_record {
+            if now - last_record < self.interval {
+                return;
+            }
+        }
+        match self.history.record(resources, now) {
+            Ok(()) => self.last_record = Some(now),
+            Err(e) => error!("could not record history: {e}"),
+        }
+    }
+}
+
+// SQLite calls are blocking, run them outside of the async workers.
+pub fn record(recorder: &Mutex<Recorder>, resources: &Resources) {
+    tokio::task::block_in_place(|| match recorder.lock() {
+        Ok(mut recorder) => recorder.record(resources),
+        Err(e) => error!("could not lock history: {e}"),
+    })
+}
+
+// Compare the resources with the runs recorded before them.
+pub fn anomalies(
+    recorder: &Mutex<Recorder>,
+    resources: &Resources,
+    config: &AnomalyConfig,
+) -> Result<Anomalies, String> {
+    tokio::task::block_in_place(|| {
+        let recorder = recorder
+            .lock()
+            .map_err(|e| format!("Could not lock history: {e}"))?;
+        detect_from_history(&recorder.history, resources, config).map_err(|e| e.to_string())
+    })
+}
+
+pub async fn history(
+    State(state): State<AppState>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<HistoryEntry>>, (StatusCode, String)> {
+    let Some(recorder) = state.history.clone() else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "history is not enabled, start the exporter with --history-db".to_string(),
+        ));
+    };
+    tokio::task::spawn_blocking(move || {
+        let recorder = recorder
+            .lock()
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        let history = recorder
+            .history
+            .query(&query)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        Ok(Json(history.entries))
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+}
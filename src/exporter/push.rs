@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use log::error;
+
+use crate::{
+    metric_families,
+    output::prometheus::{
+        otlp::otlp,
+        push::{push_gateway, remote_write, PushConfig},
+    },
+    AppState,
+};
+
+#[derive(Debug, Clone)]
+pub struct PushTargets {
+    pub push_gateway: Option<String>,
+    pub remote_write: Option<String>,
+    pub otlp: Option<String>,
+    pub config: PushConfig,
+}
+
+impl PushTargets {
+    pub fn is_empty(&self) -> bool {
+        self.push_gateway.is_none() && self.remote_write.is_none() && self.otlp.is_none()
+    }
+}
+
+pub async fn push_loop(state: AppState, targets: PushTargets, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        if let Err(e) = push(&state, &targets).await {
+            error!("could not push metrics: {e}");
+        }
+    }
+}
+
+async fn push(state: &AppState, targets: &PushTargets) -> Result<(), String> {
+    let metric_families = metric_families(state).await?;
+
+    let targets = targets.clone();
+    tokio::task::spawn_blocking(move || {
+        if let Some(url) = &targets.push_gateway {
+            push_gateway(url, &targets.config, &metric_families).map_err(|e| e.to_string())?;
+        }
+        if let Some(url) = &targets.remote_write {
+            remote_write(url, &targets.config, &metric_families).map_err(|e| e.to_string())?;
+        }
+        if let Some(url) = &targets.otlp {
+            otlp(url, &targets.config, &metric_families).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {e}"))?
+}
@@ -0,0 +1,46 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use log::info;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+
+pub fn server_config(
+    cert_path: &str,
+    key_path: &str,
+    client_ca_path: Option<&str>,
+) -> Result<ServerConfig, Box<dyn Error>> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .map_err(|e| format!("cannot read certificate {cert_path}: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("cannot parse certificate {cert_path}: {e}"))?;
+    if certs.is_empty() {
+        return Err(format!("no certificate found in {cert_path}").into());
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| format!("cannot read private key {key_path}: {e}"))?;
+
+    let builder = ServerConfig::builder();
+    let mut config = match client_ca_path {
+        Some(client_ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for ca in CertificateDer::pem_file_iter(client_ca_path)
+                .map_err(|e| format!("cannot read client CA {client_ca_path}: {e}"))?
+            {
+                let ca = ca.map_err(|e| format!("cannot parse client CA {client_ca_path}: {e}"))?;
+                roots.add(ca)?;
+            }
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots)).build()?;
+            info!("client certificates are verified against {client_ca_path}");
+            builder
+                .with_client_cert_verifier(verifier)
+                .with_single_cert(certs, key)?
+        }
+        None => builder.with_no_client_auth().with_single_cert(certs, key)?,
+    };
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(config)
+}
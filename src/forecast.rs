@@ -0,0 +1,378 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Datelike, Months, NaiveDate, SecondsFormat, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::history::CostHistory;
+
+const HOURS_PER_DAY: f64 = 24.0;
+// Two-sided 95% quantile of the normal distribution
+const Z_95: f64 = 1.96;
+// Ten years, a linear trend means nothing further
+pub const MAX_HORIZON_DAYS: i64 = 3650;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Forecast {
+    pub generated_at: String,
+    pub horizon_days: i64,
+    pub forecasts: Vec<ResourceForecast>,
+    pub total: ResourceForecast,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResourceForecast {
+    pub resource_type: String,
+    pub samples: usize,
+    // Fitted hourly price now and its change per day
+    pub price_per_hour: f64,
+    pub trend_per_day: f64,
+    pub end_of_month: Projection,
+    pub end_of_quarter: Projection,
+    pub horizon: Projection,
+}
+
+// Spend of a period with its 95% confidence band.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Projection {
+    pub from_date: String,
+    pub to_date: String,
+    pub cost: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+// Parse a horizon such as "90d", "12w" or "6m" (30 days) into days.
+pub fn parse_horizon(horizon: &str) -> Result<i64, String> {
+    let (value, factor) = match horizon.char_indices().last() {
+        Some((index, 'd')) => (horizon.get(..index), 1),
+        Some((index, 'w')) => (horizon.get(..index), 7),
+        Some((index, 'm')) => (horizon.get(..index), 30),
+        _ => (Some(horizon), 1),
+    };
+    match value
+        .and_then(|value| value.parse::<i64>().ok())
+        .and_then(|value| value.checked_mul(factor))
+    {
+        Some(days) if (1..=MAX_HORIZON_DAYS).contains(&days) => Ok(days),
+        _ => Err(format!(
+            "invalid horizon {horizon}, expected a number of days up to {MAX_HORIZON_DAYS} \
+             such as 90d, 12w or 6m"
+        )),
+    }
+}
+
+// Linear least squares fit of the hourly price over time (in days).
+struct Trend {
+    samples: usize,
+    intercept: f64,
+    slope: f64,
+    // Residual standard error and spread of the sample times
+    std_error: f64,
+    mean_day: f64,
+    sxx: f64,
+}
+
+impl Trend {
+    fn fit(points: &[(f64, f64)]) -> Option<Trend> {
+        let n = points.len() as f64;
+        if points.is_empty() {
+            return None;
+        }
+        let mean_day = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_price = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let sxx = points
+            .iter()
+            .map(|(x, _)| (x - mean_day).powi(2))
+            .sum::<f64>();
+        let sxy = points
+            .iter()
+            .map(|(x, y)| (x - mean_day) * (y - mean_price))
+            .sum::<f64>();
+        let slope = match sxx > 0.0 {
+            true => sxy / sxx,
+            false => 0.0,
+        };
+        let intercept = mean_price - slope * mean_day;
+        let std_error = match points.len() > 2 {
+            true => {
+                let ssr = points
+                    .iter()
+                    .map(|(x, y)| (y - intercept - slope * x).powi(2))
+                    .sum::<f64>();
+                (ssr / (n - 2.0)).sqrt()
+            }
+            false => 0.0,
+        };
+        Some(Trend {
+            samples: points.len(),
+            intercept,
+            slope,
+            std_error,
+            mean_day,
+            sxx,
+        })
+    }
+
+    fn price_per_hour(&self, day: f64) -> f64 {
+        (self.intercept + self.slope * day).max(0.0)
+    }
+
+    // Integrate the hourly price between the two days.
+    fn project(&self, from: f64, to: f64) -> (f64, f64) {
+        let days = to - from;
+        let cost = HOURS_PER_DAY
+            * (self.intercept * days + self.slope * (to.powi(2) - from.powi(2)) / 2.0);
+        // Band of the mean price over the period, evaluated at its middle
+        let middle = (from + to) / 2.0;
+        let leverage = match self.sxx > 0.0 {
+            true => (middle - self.mean_day).powi(2) / self.sxx,
+            false => 0.0,
+        };
+        let margin = Z_95
+            * self.std_error
+            * (1.0 / self.samples as f64 + leverage).sqrt()
+            * HOURS_PER_DAY
+            * days;
+        (cost.max(0.0), margin)
+    }
+}
+
+// Fit a trend per account, region and resource type on the history, then project the
+// spend of the current month, the current quarter and the next `horizon_days` days.
+pub fn forecast(history: &CostHistory, now: DateTime<Utc>, horizon_days: i64) -> Forecast {
+    let horizon_days = horizon_days.clamp(0, MAX_HORIZON_DAYS);
+    // Snapshots of each account and region, a resource type missing from a snapshot
+    // costs nothing at that time
+    let mut scopes = BTreeMap::<
+        (Option<&str>, Option<&str>),
+        BTreeMap<DateTime<Utc>, BTreeMap<&str, f64>>,
+    >::new();
+    for entry in &history.entries {
+        let Ok(timestamp) = DateTime::parse_from_rfc3339(&entry.timestamp) else {
+            continue;
+        };
+        *scopes
+            .entry((entry.account_id.as_deref(), entry.region.as_deref()))
+            .or_default()
+            .entry(timestamp.with_timezone(&Utc))
+            .or_default()
+            .entry(&entry.resource_type)
+            .or_default() += entry.price_per_hour;
+    }
+
+    let origin = scopes
+        .values()
+        .filter_map(|snapshots| snapshots.keys().next())
+        .min()
+        .copied()
+        .unwrap_or(now);
+    let day = |date: DateTime<Utc>| (date - origin).num_seconds() as f64 / 86400.0;
+
+    let today = now.date_naive();
+    let month_start = today.with_day(1).unwrap_or(today);
+    let quarter_start = NaiveDate::from_ymd_opt(today.year(), (today.month0() / 3) * 3 + 1, 1)
+        .unwrap_or(month_start);
+    let horizon_end = TimeDelta::try_days(horizon_days)
+        .and_then(|horizon| today.checked_add_signed(horizon))
+        .unwrap_or(today);
+    let periods = [
+        (month_start, month_start + Months::new(1)),
+        (quarter_start, quarter_start + Months::new(3)),
+        (today, horizon_end),
+    ];
+    let bounds = periods.map(|(from, to)| {
+        let from_day = day(from.and_time(Default::default()).and_utc());
+        let to_day = day(to.and_time(Default::default()).and_utc());
+        (from, to, from_day, to_day)
+    });
+
+    let mut resource_types = BTreeMap::<&str, Vec<ResourceForecast>>::new();
+    for snapshots in scopes.values() {
+        let types = snapshots
+            .values()
+            .flat_map(|prices| prices.keys().copied())
+            .collect::<BTreeSet<&str>>();
+        for resource_type in types {
+            let samples = snapshots
+                .iter()
+                .map(|(timestamp, prices)| {
+                    let price = prices.get(resource_type).copied().unwrap_or_default();
+                    (day(*timestamp), price)
+                })
+                .collect::<Vec<(f64, f64)>>();
+            let Some(trend) = Trend::fit(&samples) else {
+                continue;
+            };
+            let [end_of_month, end_of_quarter, horizon] =
+                bounds.map(|(from, to, from_day, to_day)| {
+                    let (cost, margin) = trend.project(from_day, to_day);
+                    Projection {
+                        from_date: from.to_string(),
+                        to_date: to.to_string(),
+                        cost,
+                        lower: (cost - margin).max(0.0),
+                        upper: cost + margin,
+                    }
+                });
+            resource_types
+                .entry(resource_type)
+                .or_default()
+                .push(ResourceForecast {
+                    resource_type: resource_type.to_string(),
+                    samples: trend.samples,
+                    price_per_hour: trend.price_per_hour(day(now)),
+                    trend_per_day: trend.slope,
+                    end_of_month,
+                    end_of_quarter,
+                    horizon,
+                });
+        }
+    }
+
+    // The accounts and regions of a resource type are summed
+    let forecasts = resource_types
+        .into_iter()
+        .map(|(resource_type, scoped)| combine(resource_type, &scoped, periods))
+        .collect::<Vec<ResourceForecast>>();
+    let total = combine("Total", &forecasts, periods);
+    Forecast {
+        generated_at: now.to_rfc3339_opts(SecondsFormat::Secs, true),
+        horizon_days,
+        forecasts,
+        total,
+    }
+}
+
+// Sum of forecasts, assuming independent errors for the band.
+fn combine(
+    resource_type: &str,
+    forecasts: &[ResourceForecast],
+    periods: [(NaiveDate, NaiveDate); 3],
+) -> ResourceForecast {
+    let [month, quarter, horizon] = periods;
+    ResourceForecast {
+        resource_type: resource_type.to_string(),
+        samples: forecasts
+            .iter()
+            .map(|f| f.samples)
+            .max()
+            .unwrap_or_default(),
+        price_per_hour: forecasts.iter().fold(0.0, |sum, f| sum + f.price_per_hour),
+        trend_per_day: forecasts.iter().fold(0.0, |sum, f| sum + f.trend_per_day),
+        end_of_month: sum(forecasts.iter().map(|f| &f.end_of_month), month),
+        end_of_quarter: sum(forecasts.iter().map(|f| &f.end_of_quarter), quarter),
+        horizon: sum(forecasts.iter().map(|f| &f.horizon), horizon),
+    }
+}
+
+fn sum<'a>(
+    projections: impl Iterator<Item = &'a Projection>,
+    (from, to): (NaiveDate, NaiveDate),
+) -> Projection {
+    let mut cost = 0.0;
+    let mut variance = 0.0;
+    for projection in projections {
+        cost += projection.cost;
+        variance += ((projection.upper - projection.cost) / Z_95).powi(2);
+    }
+    let margin = Z_95 * variance.sqrt();
+    Projection {
+        from_date: from.to_string(),
+        to_date: to.to_string(),
+        cost,
+        lower: (cost - margin).max(0.0),
+        upper: cost + margin,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::HistoryEntry;
+
+    fn entry(day: u32, account_id: &str, resource_type: &str, price: f64) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: format!("2024-05-{day:02}T00:00:00Z"),
+            account_id: Some(account_id.to_string()),
+            region: Some(String::from("eu-west-2")),
+            resource_type: resource_type.to_string(),
+            count: 1,
+            price_per_hour: price,
+            price_per_month: price * 730.0,
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-05-11T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parse_horizon_units() {
+        assert_eq!(parse_horizon("90d"), Ok(90));
+        assert_eq!(parse_horizon("12w"), Ok(84));
+        assert_eq!(parse_horizon("6m"), Ok(180));
+        assert_eq!(parse_horizon("30"), Ok(30));
+    }
+
+    #[test]
+    fn parse_horizon_rejects_invalid_values() {
+        for horizon in ["", "d", "0d", "-1d", "1y", "3651d", "9223372036854775807m"] {
+            assert!(parse_horizon(horizon).is_err(), "{horizon}");
+        }
+        assert_eq!(parse_horizon("3650d"), Ok(MAX_HORIZON_DAYS));
+    }
+
+    #[test]
+    fn fit_exact_line() {
+        let trend = Trend::fit(&[(0.0, 1.0), (1.0, 3.0), (2.0, 5.0), (3.0, 7.0)]).unwrap();
+        assert!((trend.slope - 2.0).abs() < 1e-9);
+        assert!((trend.intercept - 1.0).abs() < 1e-9);
+        assert!(trend.std_error.abs() < 1e-9);
+        assert!((trend.price_per_hour(4.0) - 9.0).abs() < 1e-9);
+        // 24 hours at 1 then 3 per hour on average over the first day
+        let (cost, margin) = trend.project(0.0, 1.0);
+        assert!((cost - 48.0).abs() < 1e-9);
+        assert!(margin.abs() < 1e-9);
+    }
+
+    #[test]
+    fn fit_single_sample_is_flat() {
+        assert!(Trend::fit(&[]).is_none());
+        let trend = Trend::fit(&[(5.0, 2.0)]).unwrap();
+        assert_eq!(trend.slope, 0.0);
+        assert_eq!(trend.price_per_hour(100.0), 2.0);
+    }
+
+    #[test]
+    fn accounts_are_fitted_separately() {
+        // Two accounts with a constant cost, recorded at different times: mixing their
+        // snapshots would see the cost alternate between 1 and 2
+        let history = CostHistory {
+            entries: (1..=10)
+                .map(|day| match day % 2 {
+                    0 => entry(day, "111", "Vm", 1.0),
+                    _ => entry(day, "222", "Vm", 2.0),
+                })
+                .collect(),
+        };
+        let forecast = forecast(&history, now(), 10);
+
+        assert_eq!(forecast.forecasts.len(), 1);
+        let vm = &forecast.forecasts[0];
+        assert!((vm.price_per_hour - 3.0).abs() < 1e-9);
+        assert!(vm.trend_per_day.abs() < 1e-9);
+        assert!((vm.horizon.cost - 3.0 * 24.0 * 10.0).abs() < 1e-6);
+        assert!((forecast.total.horizon.cost - vm.horizon.cost).abs() < 1e-9);
+    }
+
+    #[test]
+    fn horizon_is_capped() {
+        let history = CostHistory {
+            entries: vec![entry(1, "111", "Vm", 1.0)],
+        };
+        let forecast = forecast(&history, now(), i64::MAX);
+        assert_eq!(forecast.horizon_days, MAX_HORIZON_DAYS);
+    }
+}
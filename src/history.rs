@@ -0,0 +1,431 @@
+use std::{collections::BTreeMap, error::Error};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::warn;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{Resource, Resources, Tags};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS snapshots (
+    id INTEGER PRIMARY KEY,
+    timestamp TEXT NOT NULL,
+    account_id TEXT,
+    region TEXT
+);
+CREATE INDEX IF NOT EXISTS snapshots_timestamp ON snapshots (timestamp);
+CREATE TABLE IF NOT EXISTS resources (
+    snapshot_id INTEGER NOT NULL REFERENCES snapshots (id) ON DELETE CASCADE,
+    resource_type TEXT NOT NULL,
+    resource_id TEXT,
+    -- JSON object of the resource tags
+    tags TEXT,
+    price_per_hour REAL NOT NULL,
+    price_per_month REAL,
+    resource TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS resources_snapshot ON resources (snapshot_id);
+";
+
+// Priced resources stored in a SQLite database, one snapshot per run.
+pub struct History {
+    conn: Connection,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct HistoryQuery {
+    // Dates (YYYY-MM-DD) or RFC3339 timestamps, to_date is excluded
+    pub from_date: Option<String>,
+    pub to_date: Option<String>,
+    pub account_id: Option<String>,
+    pub region: Option<String>,
+    pub resource_type: Option<String>,
+    pub resource_id: Option<String>,
+    // KEY=VALUE, or KEY to match any value
+    pub tag: Option<String>,
+}
+
+impl HistoryQuery {
+    // Tag key and value, the value is None to match any value of the key
+    fn tag_filter(&self) -> Option<(&str, Option<&str>)> {
+        self.tag.as_deref().map(|tag| match tag.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (tag, None),
+        })
+    }
+
+    // Same filters as the history store, for inventories loaded from files
+    pub fn matches(&self, resource: &Resource) -> bool {
+        let filters = [
+            (&self.account_id, resource.account_id()),
+            (&self.region, resource.region()),
+            (&self.resource_type, Some(resource.resource_type())),
+            (&self.resource_id, resource.resource_id()),
+        ];
+        for (expected, value) in filters {
+            if let Some(expected) = expected {
+                if value != Some(expected.as_str()) {
+                    return false;
+                }
+            }
+        }
+        match self.tag_filter() {
+            Some((key, value)) => resource
+                .tags()
+                .and_then(|tags| tags.get(key))
+                .is_some_and(|tag_value| value.is_none_or(|value| value == tag_value)),
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: String,
+    pub account_id: Option<String>,
+    pub region: Option<String>,
+    pub resource_type: String,
+    pub count: i64,
+    pub price_per_hour: f64,
+    pub price_per_month: f64,
+}
+
+pub struct CostHistory {
+    pub entries: Vec<HistoryEntry>,
+}
+
+// Price of a single resource, as stored in the history.
+#[derive(Debug, Clone)]
+pub struct CostSample {
+    pub resource_type: String,
+    pub account_id: Option<String>,
+    pub tags: Tags,
+    pub price_per_hour: f64,
+}
+
+impl CostSample {
+    pub fn from_resources(resources: &Resources) -> Result<Vec<CostSample>, Box<dyn Error>> {
+        let mut samples = Vec::new();
+        for resource in &resources.resources {
+            samples.push(CostSample {
+                resource_type: resource.resource_type().to_string(),
+                account_id: resource.account_id().map(String::from),
+                tags: resource.tags().cloned().unwrap_or_default(),
+                price_per_hour: resource.price_per_hour()? as f64,
+            });
+        }
+        Ok(samples)
+    }
+}
+
+impl History {
+    pub fn open(path: &str) -> Result<History, Box<dyn Error>> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(History { conn })
+    }
+
+    // Append the resources as a new snapshot, they must have been computed.
+    pub fn record(
+        &mut self,
+        resources: &Resources,
+        timestamp: DateTime<Utc>,
+    ) -> Result<(), Box<dyn Error>> {
+        let first = resources.resources.first();
+        let account_id = first.and_then(|r| r.account_id());
+        let region = first.and_then(|r| r.region());
+
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO snapshots (timestamp, account_id, region) VALUES (?1, ?2, ?3)",
+            params![
+                timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+                account_id,
+                region
+            ],
+        )?;
+        let snapshot_id = tx.last_insert_rowid();
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO resources (snapshot_id, resource_type, resource_id, tags, \
+                 price_per_hour, price_per_month, resource) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for resource in &resources.resources {
+                insert.execute(params![
+                    snapshot_id,
+                    resource.resource_type(),
+                    resource.resource_id(),
+                    resource.tags().map(serde_json::to_string).transpose()?,
+                    resource.price_per_hour()?,
+                    resource.price_per_month(),
+                    serde_json::to_string(resource)?,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Cost per snapshot and resource type of the resources matching the query.
+    pub fn query(&self, query: &HistoryQuery) -> Result<CostHistory, Box<dyn Error>> {
+        let (tag_key, tag_value) = query.tag_filter().unzip();
+
+        let mut statement = self.conn.prepare(
+            "SELECT s.timestamp, s.account_id, s.region, r.resource_type, COUNT(*), \
+             SUM(r.price_per_hour), TOTAL(r.price_per_month) \
+             FROM resources r JOIN snapshots s ON s.id = r.snapshot_id \
+             WHERE (?1 IS NULL OR s.timestamp >= ?1) \
+             AND (?2 IS NULL OR s.timestamp < ?2) \
+             AND (?3 IS NULL OR s.account_id = ?3) \
+             AND (?4 IS NULL OR s.region = ?4) \
+             AND (?5 IS NULL OR r.resource_type = ?5) \
+             AND (?6 IS NULL OR r.resource_id = ?6) \
+             AND (?7 IS NULL OR EXISTS (SELECT 1 FROM json_each(r.tags) t \
+             WHERE t.key = ?7 AND (?8 IS NULL OR t.value = ?8))) \
+             GROUP BY s.id, r.resource_type \
+             ORDER BY s.timestamp, r.resource_type",
+        )?;
+        let entries = statement
+            .query_map(
+                params![
+                    query.from_date,
+                    query.to_date,
+                    query.account_id,
+                    query.region,
+                    query.resource_type,
+                    query.resource_id,
+                    tag_key,
+                    tag_value.flatten(),
+                ],
+                |row| {
+                    Ok(HistoryEntry {
+                        timestamp: row.get(0)?,
+                        account_id: row.get(1)?,
+                        region: row.get(2)?,
+                        resource_type: row.get(3)?,
+                        count: row.get(4)?,
+                        price_per_hour: row.get(5)?,
+                        price_per_month: row.get(6)?,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<HistoryEntry>, rusqlite::Error>>()?;
+
+        Ok(CostHistory { entries })
+    }
+
+    // Last inventory of the account and region recorded between the two dates.
+    pub fn inventory(
+        &self,
+        account_id: Option<&str>,
+        region: Option<&str>,
+        from_date: &str,
+        to_date: &str,
+    ) -> Result<Option<Resources>, Box<dyn Error>> {
+        let mut statement = self.conn.prepare(
+            "SELECT r.resource FROM resources r \
+             WHERE r.snapshot_id = (SELECT id FROM snapshots \
+             WHERE account_id IS ?1 AND region IS ?2 AND timestamp >= ?3 AND timestamp < ?4 \
+             ORDER BY timestamp DESC LIMIT 1)",
+        )?;
+        let mut resources = Vec::new();
+        let mut rows = statement.query(params![account_id, region, from_date, to_date])?;
+        while let Some(row) = rows.next()? {
+            let resource: String = row.get(0)?;
+            resources.push(serde_json::from_str::<Resource>(&resource)?);
+        }
+        match resources.is_empty() {
+            true => Ok(None),
+            false => Ok(Some(Resources { resources })),
+        }
+    }
+
+    // Resources of the last `runs` snapshots of the account and region taken before the date,
+    // oldest first.
+    pub fn samples(
+        &self,
+        account_id: Option<&str>,
+        region: Option<&str>,
+        before: &str,
+        runs: usize,
+    ) -> Result<Vec<Vec<CostSample>>, Box<dyn Error>> {
+        let mut statement = self.conn.prepare(
+            "SELECT s.id, r.resource_type, s.account_id, r.tags, r.price_per_hour \
+             FROM resources r JOIN snapshots s ON s.id = r.snapshot_id \
+             WHERE s.id IN (SELECT id FROM snapshots \
+             WHERE account_id IS ?1 AND region IS ?2 AND timestamp < ?3 \
+             ORDER BY timestamp DESC LIMIT ?4) \
+             ORDER BY s.timestamp, s.id",
+        )?;
+        let rows =
+            statement.query_map(params![account_id, region, before, runs as i64], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<String>>(3)?,
+                    CostSample {
+                        resource_type: row.get(1)?,
+                        account_id: row.get(2)?,
+                        tags: Tags::new(),
+                        price_per_hour: row.get(4)?,
+                    },
+                ))
+            })?;
+
+        let mut snapshots = Vec::<Vec<CostSample>>::new();
+        let mut last_id = None;
+        for row in rows {
+            let (snapshot_id, tags, mut sample) = row?;
+            if let Some(tags) = tags {
+                sample.tags = serde_json::from_str(&tags)?;
+            }
+            match (last_id == Some(snapshot_id), snapshots.last_mut()) {
+                (true, Some(snapshot)) => snapshot.push(sample),
+                _ => snapshots.push(vec![sample]),
+            }
+            last_id = Some(snapshot_id);
+        }
+        Ok(snapshots)
+    }
+}
+
+impl CostHistory {
+    // Group dated inventories (e.g. archived --format=json outputs) like the history store does.
+    pub fn from_snapshots(
+        snapshots: &[Resources],
+        query: &HistoryQuery,
+    ) -> Result<CostHistory, Box<dyn Error>> {
+        let mut entries = Vec::new();
+        for resources in snapshots {
+            let Some(read_date) = resources
+                .resources
+                .iter()
+                .filter_map(|resource| resource.read_date())
+                .find(|read_date| !read_date.is_empty())
+            else {
+                warn!("skipping an inventory without read date");
+                continue;
+            };
+            let timestamp = DateTime::parse_from_rfc3339(read_date)?
+                .with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::Secs, true);
+            if query
+                .from_date
+                .as_ref()
+                .is_some_and(|from| &timestamp < from)
+                || query.to_date.as_ref().is_some_and(|to| &timestamp >= to)
+            {
+                continue;
+            }
+
+            let mut resource_types = BTreeMap::<&str, HistoryEntry>::new();
+            for resource in resources.resources.iter().filter(|r| query.matches(r)) {
+                let entry = resource_types
+                    .entry(resource.resource_type())
+                    .or_insert_with(|| HistoryEntry {
+                        timestamp: timestamp.clone(),
+                        account_id: resource.account_id().map(String::from),
+                        region: resource.region().map(String::from),
+                        resource_type: resource.resource_type().to_string(),
+                        count: 0,
+                        price_per_hour: 0.0,
+                        price_per_month: 0.0,
+                    });
+                entry.count += match resource {
+                    Resource::Aggregate(aggregate) => aggregate.count as i64,
+                    _ => 1,
+                };
+                entry.price_per_hour += resource.price_per_hour()? as f64;
+                entry.price_per_month += resource.price_per_month().unwrap_or_default() as f64;
+            }
+            entries.extend(resource_types.into_values());
+        }
+        entries.sort_by(|a, b| {
+            a.timestamp
+                .cmp(&b.timestamp)
+                .then_with(|| a.resource_type.cmp(&b.resource_type))
+        });
+
+        Ok(CostHistory { entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::volumes::Volume;
+
+    fn volume(resource_id: &str, tags: &[(&str, &str)]) -> Resource {
+        Resource::Volume(Volume {
+            resource_id: Some(resource_id.to_string()),
+            tags: Some(
+                tags.iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            ),
+            price_per_hour: Some(1.0),
+            ..Volume::default()
+        })
+    }
+
+    fn tag_query(tag: &str) -> HistoryQuery {
+        HistoryQuery {
+            tag: Some(tag.to_string()),
+            ..HistoryQuery::default()
+        }
+    }
+
+    #[test]
+    fn tags_with_separators_match_exactly() {
+        let mut history = History::open(":memory:").unwrap();
+        let resources = Resources {
+            resources: vec![
+                volume("vol-1", &[("team", "a,b=c")]),
+                volume("vol-2", &[("team", "a"), ("b", "c")]),
+            ],
+        };
+        history.record(&resources, Utc::now()).unwrap();
+
+        let count = |tag: &str| {
+            let history = history.query(&tag_query(tag)).unwrap();
+            history.entries.iter().map(|entry| entry.count).sum::<i64>()
+        };
+        assert_eq!(count("team=a,b=c"), 1);
+        assert_eq!(count("team=a"), 1);
+        assert_eq!(count("b=c"), 1);
+        assert_eq!(count("team"), 2);
+        assert_eq!(count("a"), 0);
+
+        let matches = |tag: &str| {
+            resources
+                .resources
+                .iter()
+                .filter(|resource| tag_query(tag).matches(resource))
+                .count()
+        };
+        assert_eq!(matches("team=a,b=c"), 1);
+        assert_eq!(matches("team"), 2);
+        assert_eq!(matches("a"), 0);
+    }
+
+    #[test]
+    fn samples_keep_the_tags() {
+        let mut history = History::open(":memory:").unwrap();
+        let resources = Resources {
+            resources: vec![volume("vol-1", &[("team", "a,b=c")])],
+        };
+        history
+            .record(&resources, DateTime::UNIX_EPOCH.with_timezone(&Utc))
+            .unwrap();
+
+        let samples = history
+            .samples(Some(""), Some(""), "2100-01-01T00:00:00Z", 1)
+            .unwrap();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(
+            samples[0][0].tags.get("team").map(String::as_str),
+            Some("a,b=c")
+        );
+    }
+}
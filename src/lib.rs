@@ -1,4 +1,7 @@
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+pub mod anomaly;
 pub mod core;
+pub mod forecast;
+pub mod history;
 pub mod oapi;
@@ -1,4 +1,4 @@
-use crate::core::Resources;
+use crate::core::{Resources, Tags};
 use crate::VERSION;
 use aws_config::{BehaviorVersion, Region, SdkConfig};
 use aws_credential_types::provider::SharedCredentialsProvider;
@@ -14,9 +14,10 @@ use outscale_api::apis::profile::{Profile, ProfileBuilder};
 use outscale_api::apis::subregion_api::read_subregions;
 use outscale_api::models::ConsumptionEntry;
 use outscale_api::models::{
-    Account, CatalogEntry, FlexibleGpu, Image, NatService, PublicIp, ReadAccountsRequest,
-    ReadAccountsResponse, ReadCatalogRequest, ReadCatalogResponse, ReadSubregionsRequest,
-    ReadSubregionsResponse, Snapshot, Vm, VmType, Volume,
+    Account, CatalogEntry, FlexibleGpu, Image, LoadBalancer, NatService, PublicIp,
+    ReadAccountsRequest, ReadAccountsResponse, ReadCatalogRequest, ReadCatalogResponse,
+    ReadSubregionsRequest, ReadSubregionsResponse, ResourceTag, Snapshot, Vm, VmType, Volume,
+    VpnConnection,
 };
 use std::collections::HashMap;
 use std::convert::From;
@@ -41,6 +42,8 @@ type ImageId = String;
 // use catalog_entry() to ease process
 type CatalogId = String;
 type VmTypeName = String;
+// Consumption entries are merged per account and catalog id
+type ConsumptionId = (String, CatalogId);
 
 mod dedicated_instances;
 mod digest;
@@ -80,16 +83,26 @@ pub struct Input {
     pub public_ips: HashMap<PublicIpId, PublicIp>,
     pub filters: Option<Filter>,
     pub flexible_gpus: HashMap<FlexibleGpuId, FlexibleGpu>,
-    pub load_balancers: Vec<LoadbalancerId>,
-    pub vpns: Vec<VpnId>,
+    pub load_balancers: HashMap<LoadbalancerId, LoadBalancer>,
+    pub vpns: HashMap<VpnId, VpnConnection>,
     pub buckets: HashMap<BucketId, OosBucket>,
-    pub consumption: HashMap<CatalogId, ConsumptionEntry>,
+    pub consumption: HashMap<ConsumptionId, ConsumptionEntry>,
+    // Fetch the consumption of all linked accounts (paying account only)
+    pub overall: bool,
+    // Time the catalog was read, the API does not date the catalog itself
+    pub catalog_fetched_at: Option<DateTime<Utc>>,
+    // The bucket listing failed, Oos is missing from the resources (other fetch errors abort the run)
+    pub oos_fetch_failed: bool,
 }
 
 impl Input {
     pub fn new(profile_name: Option<String>) -> Result<Input, Box<dyn error::Error>> {
         let (config, aws_config) = Input::get_config(profile_name)?;
-        Ok(Input {
+        Ok(Input::with_config(config, aws_config))
+    }
+
+    fn with_config(config: Configuration, aws_config: SdkConfig) -> Input {
+        Input {
             config,
             aws_config,
             vms: HashMap::new(),
@@ -108,11 +121,14 @@ impl Input {
             filters: None,
             flexible_gpus: HashMap::new(),
             use_dedicated_instance: false,
-            load_balancers: Vec::new(),
-            vpns: Vec::new(),
+            load_balancers: HashMap::new(),
+            vpns: HashMap::new(),
             buckets: HashMap::new(),
             consumption: HashMap::new(),
-        })
+            overall: false,
+            catalog_fetched_at: None,
+            oos_fetch_failed: false,
+        }
     }
 
     fn get_config(profile: Option<String>) -> Result<(Configuration, SdkConfig), Box<dyn Error>> {
@@ -230,6 +246,7 @@ impl Input {
                 return Ok(());
             }
         };
+        self.catalog_fetched_at = Some(Utc::now());
         for entry in catalog {
             let _type = match &entry._type {
                 Some(t) => t.clone(),
@@ -364,3 +381,16 @@ impl From<Input> for Resources {
         input.build_resources()
     }
 }
+
+// Resource tags by key, None when the resource has no tag.
+fn format_tags(tags: &Option<Vec<ResourceTag>>) -> Option<Tags> {
+    let tags = tags
+        .iter()
+        .flatten()
+        .map(|tag| (tag.key.clone(), tag.value.clone()))
+        .collect::<Tags>();
+    match tags.is_empty() {
+        true => None,
+        false => Some(tags),
+    }
+}
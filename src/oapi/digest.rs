@@ -1,5 +1,9 @@
-use std::{collections::HashMap, error};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error,
+};
 
+use chrono::{Days, NaiveDate};
 use lazy_static::lazy_static;
 use log::{info, warn};
 use outscale_api::{
@@ -9,254 +13,803 @@ use outscale_api::{
 use regex::Regex;
 
 use crate::{
-    core::digest::{match_entry_id_resource_type, Digest},
+    core::{
+        digest::{
+            compute_component_drift, compute_drift, compute_month_to_date, compute_resource_drift,
+            match_entry_id_resource_type, match_entry_id_vm_component, month_to_date_period,
+            AccountDrift, AccountDrifts, DailyDrift, DailyDrifts, Digest, Drifts, MonthToDate,
+            ResourceDrifts, ResourceKey, UnmappedEntry, UNMAPPED,
+        },
+        focus::{charge_period, service, Charge, CONSUMPTION},
+        vms::Vm,
+        Resource, Resources,
+    },
     oapi::vms::VmSpecs,
 };
 
-use super::Input;
+use super::{CatalogId, ConsumptionId, Input};
 impl Input {
-    pub fn fetch_digest(
+    // Compare the aggregated resources with the consumption billed between the two dates.
+    pub fn drift(
         &mut self,
+        resources: &Resources,
         from_date: &str,
         to_date: &str,
-    ) -> Result<(), Box<dyn error::Error>> {
-        let result: ReadConsumptionAccountResponse = {
-            let request =
-                ReadConsumptionAccountRequest::new(from_date.to_owned(), to_date.to_owned());
-            read_consumption_account(&self.config, Some(request))?
-        };
+    ) -> Result<Drifts, Box<dyn error::Error>> {
+        self.fetch_catalog()?;
+        self.fetch_vm_types()?; // needed to extract information from boxes
+        self.fetch_digest(from_date, to_date)?;
+        let mut digests = HashMap::<String, Digest>::new();
+        self.fill_digest(&mut digests);
 
-        let entries = match result.consumption_entries {
-            Some(catalog) => catalog,
-            None => {
-                warn!("no consumption provided");
-                return Ok(());
+        compute_drift(digests, resources, from_date, to_date)
+    }
+
+    // Compare the consumption of each day with the aggregated inventory of that day, or
+    // with the fallback inventory when none was archived.
+    pub fn daily_drift(
+        &mut self,
+        inventories: &BTreeMap<NaiveDate, Resources>,
+        fallback: &Resources,
+        from_date: NaiveDate,
+        to_date: NaiveDate,
+    ) -> Result<DailyDrifts, Box<dyn error::Error>> {
+        self.fetch_catalog()?;
+        self.fetch_vm_types()?; // needed to extract information from boxes
+
+        let mut drifts = Vec::new();
+        for date in from_date.iter_days().take_while(|date| *date < to_date) {
+            let next_date = date + Days::new(1);
+            let (from, to) = (date.to_string(), next_date.to_string());
+            self.fetch_digest(&from, &to)?;
+            let mut digests = HashMap::<String, Digest>::new();
+            self.fill_digest(&mut digests);
+
+            let resources = inventories.get(&date).unwrap_or(fallback);
+            for drift in compute_drift(digests, resources, &from, &to)?.drifts {
+                drifts.push(DailyDrift {
+                    date: from.clone(),
+                    drift,
+                });
             }
-        };
+        }
+        Ok(DailyDrifts { drifts })
+    }
 
-        self.consumption.clear();
-        for entry in entries {
-            let service = match &entry.service {
-                Some(t) => t.clone(),
-                None => {
-                    warn!("digest entry has no service");
-                    continue;
-                }
-            };
+    // Consumption billed since the beginning of the month plus the projection of the
+    // aggregated resources until its end.
+    pub fn month_to_date(
+        &mut self,
+        resources: &Resources,
+        today: NaiveDate,
+    ) -> Result<MonthToDate, Box<dyn error::Error>> {
+        let (from_date, to_date, _) = month_to_date_period(today);
+        let mut digests = HashMap::<String, Digest>::new();
+        // Nothing has been billed yet on the first day of the month
+        if from_date < to_date {
+            self.fetch_catalog()?;
+            self.fetch_vm_types()?; // needed to extract information from boxes
+            self.fetch_digest(&from_date.to_string(), &to_date.to_string())?;
+            self.fill_digest(&mut digests);
+        }
 
-            let operation = match &entry.operation {
-                Some(t) => t.clone(),
-                None => {
-                    warn!("digest entry has no operation");
-                    continue;
-                }
-            };
+        compute_month_to_date(digests, resources, today)
+    }
 
-            let _type = match &entry._type {
-                Some(t) => t.clone(),
-                None => {
-                    warn!("digest entry has no operation");
-                    continue;
-                }
+    // Compare the consumption of each account with its aggregated resources, the
+    // consumption of the linked accounts is fetched when `overall` is set.
+    pub fn account_drift(
+        &mut self,
+        resources: Resources,
+        from_date: &str,
+        to_date: &str,
+    ) -> Result<AccountDrifts, Box<dyn error::Error>> {
+        self.fetch_catalog()?;
+        self.fetch_vm_types()?; // needed to extract information from boxes
+        self.fetch_digest(from_date, to_date)?;
+
+        let mut accounts = BTreeMap::<String, Vec<Resource>>::new();
+        for (account_id, _) in self.consumption.keys() {
+            accounts.entry(account_id.clone()).or_default();
+        }
+        for resource in resources.resources {
+            accounts
+                .entry(resource.account_id().unwrap_or_default().to_string())
+                .or_default()
+                .push(resource);
+        }
+
+        let mut drifts = Vec::new();
+        for (account_id, resources) in accounts {
+            let mut digests = HashMap::<String, Digest>::new();
+            self.fill_account_digest(Some(&account_id), &mut digests);
+            let resources = Resources { resources }.aggregate();
+            for drift in compute_drift(digests, &resources, from_date, to_date)?.drifts {
+                drifts.push(AccountDrift {
+                    account_id: account_id.clone(),
+                    drift,
+                });
+            }
+        }
+        Ok(AccountDrifts { drifts })
+    }
+
+    // Compare the resources with the consumption billed between the two dates, VMs are
+    // broken down by pricing component (vCPU, RAM, license per product code, box and
+    // dedicated surcharge).
+    pub fn component_drift(
+        &mut self,
+        resources: &Resources,
+        from_date: &str,
+        to_date: &str,
+    ) -> Result<Drifts, Box<dyn error::Error>> {
+        self.fetch_catalog()?;
+        self.fetch_vm_types()?; // needed to extract information from boxes
+        self.fetch_digest(from_date, to_date)?;
+
+        let mut digests = HashMap::<String, Digest>::new();
+        for ((_, id), entry) in &self.consumption {
+            let Some((_, components)) = self.price_entry_components(id, entry) else {
+                add_unmapped(&mut digests, self.unmapped_entry(id, entry));
+                continue;
             };
+            for (component, price) in components {
+                let digest = digests.entry(component).or_default();
+                digest.price = Some(digest.price.unwrap_or(0.0) + price);
+            }
+        }
 
-            let entry_id = format!("{service}/{_type}/{operation}");
-            match self.consumption.get(&entry_id) {
-                Some(e) => {
-                    self.consumption.insert(
-                        entry_id,
-                        ConsumptionEntry {
-                            account_id: entry.account_id,
-                            category: entry.category,
-                            from_date: entry.from_date,
-                            operation: entry.operation,
-                            paying_account_id: entry.paying_account_id,
-                            resource_id: entry.resource_id,
-                            service: entry.service,
-                            subregion_name: None,
-                            title: entry.title,
-                            to_date: entry.to_date,
-                            _type: entry._type,
-                            value: Some(entry.value.unwrap_or(0.0) + e.value.unwrap_or(0.0)),
-                            price: entry.price,
-                            unit_price: entry.unit_price,
-                        },
-                    );
+        let mut estimates = HashMap::<String, f32>::new();
+        for resource in &resources.resources {
+            match resource {
+                Resource::Vm(vm) => {
+                    let product_price = |code: &str| {
+                        self.catalog_entry(
+                            "TinaOS-FCU",
+                            "ProductUsage",
+                            &format!("RunInstances-{code}-OD"),
+                        )
+                    };
+                    for (component, price) in vm_components(vm, product_price) {
+                        *estimates.entry(component).or_default() += price;
+                    }
+                }
+                Resource::Aggregate(_) => {
+                    warn!("cannot handle aggregated resources");
                 }
-                None => {
-                    self.consumption.insert(entry_id, entry);
+                resource => {
+                    *estimates
+                        .entry(resource.resource_type().to_string())
+                        .or_default() += resource.price_per_hour()?;
                 }
             }
         }
 
+        compute_component_drift(digests, estimates, from_date, to_date)
+    }
+
+    // Compare each resource with the consumption billed for its id between the two dates.
+    pub fn resource_drift(
+        &mut self,
+        resources: &Resources,
+        from_date: &str,
+        to_date: &str,
+    ) -> Result<ResourceDrifts, Box<dyn error::Error>> {
+        self.fetch_catalog()?;
+        self.fetch_vm_types()?; // needed to extract information from boxes
+
+        let entries = self
+            .read_consumption(from_date, to_date, true)?
+            .unwrap_or_else(|| {
+                warn!("no consumption provided");
+                Vec::new()
+            });
+        info!("fetched {} resource consumption entries", entries.len());
+
+        // Entries are kept per resource instead of being merged per catalog id
+        let mut digests = HashMap::<ResourceKey, f32>::new();
+        for entry in &entries {
+            let Some(entry_id) = consumption_entry_id(entry) else {
+                continue;
+            };
+            let (category, price) = self.price_entry(&entry_id, entry).unwrap_or_else(|| {
+                let unmapped = self.unmapped_entry(&entry_id, entry);
+                (String::from(UNMAPPED), unmapped.price)
+            });
+            *digests
+                .entry((entry.resource_id.clone(), category))
+                .or_default() += price;
+        }
+
+        compute_resource_drift(digests, resources, from_date, to_date)
+    }
+
+    // Consumption of the period as FOCUS charges, one per account, catalog entry and
+    // resource.
+    pub fn consumption_charges(
+        &mut self,
+        from_date: &str,
+        to_date: &str,
+    ) -> Result<Vec<Charge>, Box<dyn error::Error>> {
+        self.fetch_catalog()?;
+        self.fetch_vm_types()?; // needed to extract information from boxes
+        self.fetch_region()?;
+        let (charge_period_start, charge_period_end, _) = charge_period(from_date, to_date)?;
+
+        let mut entries = BTreeMap::<(String, CatalogId, Option<String>), ConsumptionEntry>::new();
+        for entry in self
+            .read_consumption(from_date, to_date, true)?
+            .unwrap_or_else(|| {
+                warn!("no consumption provided");
+                Vec::new()
+            })
+        {
+            let Some(id) = consumption_entry_id(&entry) else {
+                continue;
+            };
+            let key = (
+                entry.account_id.clone().unwrap_or_default(),
+                id,
+                entry.resource_id.clone(),
+            );
+            let entry = match entries.get(&key) {
+                Some(previous) => merge_entries(previous, entry),
+                None => entry,
+            };
+            entries.insert(key, entry);
+        }
+        info!("fetched {} resource consumption entries", entries.len());
+
+        let mut charges = Vec::new();
+        for ((account_id, id, resource_id), entry) in &entries {
+            let (resource_type, effective_cost) = match self.price_entry(id, entry) {
+                Some((category, price)) => (Some(category), price),
+                None => (None, self.unmapped_entry(id, entry).price),
+            };
+            let (service_name, service_category) =
+                service(resource_type.as_deref().unwrap_or_default());
+            let pricing_quantity = entry.value.unwrap_or(0.0) as f32;
+            let list_cost = self.list_cost(id, entry).unwrap_or(effective_cost);
+            let list_unit_price = (pricing_quantity > 0.0).then(|| list_cost / pricing_quantity);
+            charges.push(Charge {
+                source: CONSUMPTION.to_string(),
+                billing_account_id: entry.paying_account_id.clone().or(Some(account_id.clone())),
+                sub_account_id: Some(account_id.clone()),
+                region_id: self.region.clone(),
+                resource_id: resource_id.clone(),
+                resource_type,
+                service_name: entry.service.clone().unwrap_or(service_name.to_string()),
+                service_category: service_category.to_string(),
+                sku_id: Some(id.clone()),
+                charge_description: entry.title.clone(),
+                charge_period_start: charge_period_start.clone(),
+                charge_period_end: charge_period_end.clone(),
+                list_unit_price,
+                pricing_quantity,
+                pricing_unit: None,
+                list_cost,
+                effective_cost,
+                billed_cost: entry.price.map_or(effective_cost, |price| price as f32),
+                tags: None,
+            });
+        }
+        Ok(charges)
+    }
+
+    pub fn fetch_digest(
+        &mut self,
+        from_date: &str,
+        to_date: &str,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let entries = self.read_consumption(from_date, to_date, false)?;
+        fill_consumption(&mut self.consumption, entries);
+
         info!("fetched {} consumption entries", self.consumption.len());
         Ok(())
     }
 
+    // Consumption entries between the two dates, with one entry per resource id when
+    // `resource_details` is set.
+    fn read_consumption(
+        &self,
+        from_date: &str,
+        to_date: &str,
+        resource_details: bool,
+    ) -> Result<Option<Vec<ConsumptionEntry>>, Box<dyn error::Error>> {
+        let mut request =
+            ReadConsumptionAccountRequest::new(from_date.to_owned(), to_date.to_owned());
+        request.show_price = Some(true);
+        if resource_details {
+            request.show_resource_details = Some(true);
+        }
+        if self.overall {
+            request.overall = Some(true);
+        }
+        let result: ReadConsumptionAccountResponse =
+            read_consumption_account(&self.config, Some(request))?;
+        Ok(result.consumption_entries)
+    }
+
     pub fn fill_digest(&self, digests: &mut HashMap<String, Digest>) {
-        for (id, entry) in &self.consumption {
-            match id {
-                s if s.starts_with("TinaOS-FCU/ProductUsage") => {
-                    lazy_static! {
-                        static ref REG: Regex =
-                            Regex::new(r"^TinaOS-FCU/ProductUsage:(.+)/RunInstances-(\d+)-OD")
-                                .unwrap();
-                    }
-                    let cap = match REG.captures_iter(s).next() {
-                        Some(cap) => cap,
-                        None => {
-                            warn!("Cannot extract tina type {}", s);
-                            continue;
-                        }
-                    };
+        self.fill_account_digest(None, digests);
+    }
 
-                    let vm_type = String::from(&cap[1]);
-                    let product_code = String::from(&cap[2]);
-
-                    // Extract cores
-                    let cores: f32 = if vm_type.starts_with("tina") {
-                        match VmSpecs::parse_tina_type(&vm_type) {
-                            None => {
-                                warn!("Cannot extract cores from tina type");
-                                continue;
-                            }
-                            Some((_, c, _, _)) => c,
-                        }
-                    } else {
-                        match VmSpecs::parse_box_type(&vm_type, self) {
-                            None => {
-                                warn!("Cannot extract cores from aws type");
-                                continue;
-                            }
-                            Some((_, c, _, _)) => c,
-                        }
-                    };
+    // Same as fill_digest with the consumption of a single account only.
+    pub fn fill_account_digest(
+        &self,
+        account_id: Option<&str>,
+        digests: &mut HashMap<String, Digest>,
+    ) {
+        for ((entry_account_id, id), entry) in &self.consumption {
+            if account_id.is_some_and(|account_id| account_id != entry_account_id) {
+                continue;
+            }
+            let Some((category, price)) = self.price_entry(id, entry) else {
+                add_unmapped(digests, self.unmapped_entry(id, entry));
+                continue;
+            };
+            let digest = digests.entry(category).or_default();
+            digest.price = Some(digest.price.unwrap_or(0.0) + price);
+        }
+    }
 
-                    // Extract product codes
-                    let Some(price_factor) =
-                        VmSpecs::compute_product_price_per_hour(cores, &product_code)
-                    else {
-                        warn!("Cannot extract price factor from product codes");
-                        continue;
-                    };
+    // Consumption entry without category or catalog price, priced with the billed price
+    // when available.
+    fn unmapped_entry(&self, id: &str, entry: &ConsumptionEntry) -> UnmappedEntry {
+        let value = entry.value.unwrap_or(0.0);
+        let unit_price = self
+            .catalog
+            .get(id)
+            .and_then(|catalog_entry| catalog_entry.unit_price)
+            .map(f64::from)
+            .or(entry.unit_price);
+        let price = match (entry.price, unit_price) {
+            (Some(price), _) => price,
+            (None, Some(unit_price)) => value * unit_price,
+            (None, None) => 0.0,
+        };
+        warn!("consumption entry {id} is unmapped");
+        UnmappedEntry {
+            entry_id: id.to_string(),
+            value,
+            price: price as f32,
+        }
+    }
 
-                    // CustomRam
-                    let Some(product_usage_catalog) = self.catalog.get(&format!(
-                        "TinaOS-FCU/ProductUsage/RunInstances-{product_code}-OD",
-                    )) else {
-                        warn!("Cannot get product code entry");
-                        continue;
-                    };
+    // Category and price of a consumption entry.
+    fn price_entry(&self, id: &str, entry: &ConsumptionEntry) -> Option<(String, f32)> {
+        let (category, components) = self.price_entry_components(id, entry)?;
+        Some((category, components.iter().map(|(_, price)| price).sum()))
+    }
+
+    // Category of a consumption entry and its price per pricing component (vCPU, RAM,
+    // license, ...), the component of a non VM entry is its category. The billed price is
+    // used when the consumption provides it, the catalog otherwise.
+    fn price_entry_components(
+        &self,
+        id: &str,
+        entry: &ConsumptionEntry,
+    ) -> Option<(String, Vec<(String, f32)>)> {
+        let Some(billed) = entry.price else {
+            return self.catalog_price_components(id, entry);
+        };
+        let billed = billed as f32;
+        match self.catalog_price_components(id, entry) {
+            // Split the billed price between the components like the catalog price
+            Some((category, components)) => {
+                let total = components.iter().map(|(_, price)| price).sum::<f32>();
+                let components = match total > 0.0 {
+                    true => components
+                        .into_iter()
+                        .map(|(component, price)| (component, billed * price / total))
+                        .collect(),
+                    false => {
+                        let component = components
+                            .into_iter()
+                            .next()
+                            .map_or(category.clone(), |(component, _)| component);
+                        vec![(component, billed)]
+                    }
+                };
+                Some((category, components))
+            }
+            None => {
+                let category = match_entry_id_resource_type(&id.to_string())?;
+                let component = match_entry_id_vm_component(id).unwrap_or(category.clone());
+                Some((category, vec![(component, billed)]))
+            }
+        }
+    }
 
-                    let price = price_factor
-                        * entry.value.unwrap_or(0.0) as f32
-                        * product_usage_catalog.unit_price.unwrap_or(0.0);
+    // Catalog price of a consumption entry, with the core factor of the product codes and
+    // the core and RAM prices of the tina boxes.
+    fn list_cost(&self, id: &str, entry: &ConsumptionEntry) -> Option<f32> {
+        let (_, components) = self.catalog_price_components(id, entry)?;
+        Some(components.iter().map(|(_, price)| price).sum())
+    }
+
+    // Same as price_entry_components, from the catalog unit prices only.
+    fn catalog_price_components(
+        &self,
+        id: &str,
+        entry: &ConsumptionEntry,
+    ) -> Option<(String, Vec<(String, f32)>)> {
+        match id {
+            s if s.starts_with("TinaOS-FCU/ProductUsage") => {
+                lazy_static! {
+                    static ref REG: Regex =
+                        Regex::new(r"^TinaOS-FCU/ProductUsage:(.+)/RunInstances-(\d+)-OD").unwrap();
+                }
+                let Some(cap) = REG.captures_iter(s).next() else {
+                    warn!("Cannot extract tina type {}", s);
+                    return None;
+                };
 
-                    let category = String::from("Vm");
-                    match digests.get(&category) {
+                let vm_type = String::from(&cap[1]);
+                let product_code = String::from(&cap[2]);
+
+                // Extract cores
+                let cores: f32 = if vm_type.starts_with("tina") {
+                    match VmSpecs::parse_tina_type(&vm_type) {
                         None => {
-                            digests.insert(category, Digest { price: Some(price) });
-                        }
-                        Some(d) => {
-                            digests.insert(
-                                category,
-                                Digest {
-                                    price: Some(d.price.unwrap_or(0.0) + price),
-                                },
-                            );
+                            warn!("Cannot extract cores from tina type");
+                            return None;
                         }
+                        Some((_, c, _, _)) => c,
                     }
-                }
-                s if s.starts_with("TinaOS-FCU/BoxUsage:tina") => {
-                    // Convert into CustomCore and CustomRam
-                    lazy_static! {
-                        static ref REG: Regex = Regex::new(r":(.+)/").unwrap();
-                    }
-                    let cap = match REG.captures_iter(s).next() {
-                        Some(cap) => cap,
+                } else {
+                    match VmSpecs::parse_box_type(&vm_type, self) {
                         None => {
-                            warn!("Cannot extract tina type {}", s);
-                            continue;
+                            warn!("Cannot extract cores from aws type");
+                            return None;
                         }
-                    };
+                        Some((_, c, _, _)) => c,
+                    }
+                };
 
-                    let tina_type = String::from(&cap[1]);
-                    let Some((generation, vcpu, ram_gb, performance)) =
-                        VmSpecs::parse_tina_type(&tina_type)
-                    else {
-                        warn!("Cannot extract value from tina type {}", s);
-                        continue;
-                    };
+                // Extract product codes
+                let Some(price_factor) =
+                    VmSpecs::compute_product_price_per_hour(cores, &product_code)
+                else {
+                    warn!("Cannot extract price factor from product codes");
+                    return None;
+                };
 
-                    // CustomRam
-                    let Some(custom_ram_catalog) =
-                        self.catalog.get("TinaOS-FCU/CustomRam/RunInstances-OD")
-                    else {
-                        warn!("Cannot get customRam entry");
-                        continue;
-                    };
+                // CustomRam
+                let Some(product_usage_catalog) = self.catalog.get(&format!(
+                    "TinaOS-FCU/ProductUsage/RunInstances-{product_code}-OD",
+                )) else {
+                    warn!("Cannot get product code entry");
+                    return None;
+                };
 
-                    // CustomCore
-                    let Some(custom_core_catalog) = self.catalog.get(&format!(
-                        "TinaOS-FCU/CustomCore:v{generation}-p{performance}/RunInstances-OD",
-                    )) else {
-                        warn!("Cannot get customCore entry");
-                        continue;
-                    };
+                let price = price_factor
+                    * entry.value.unwrap_or(0.0) as f32
+                    * product_usage_catalog.unit_price.unwrap_or(0.0);
+                Some((
+                    String::from("Vm"),
+                    vec![(format!("Vm/License:{product_code}"), price)],
+                ))
+            }
+            s if s.starts_with("TinaOS-FCU/BoxUsage:tina") => {
+                // Convert into CustomCore and CustomRam
+                lazy_static! {
+                    static ref REG: Regex = Regex::new(r":(.+)/").unwrap();
+                }
+                let Some(cap) = REG.captures_iter(s).next() else {
+                    warn!("Cannot extract tina type {}", s);
+                    return None;
+                };
 
-                    // value *(CustomRam price * ram + CustomCore price * core)
-                    let price = ram_gb
-                        * entry.value.unwrap_or(0.0) as f32
-                        * custom_ram_catalog.unit_price.unwrap_or(0.0)
-                        + vcpu
-                            * entry.value.unwrap_or(0.0) as f32
-                            * custom_core_catalog.unit_price.unwrap_or(0.0);
+                let tina_type = String::from(&cap[1]);
+                let Some((generation, vcpu, ram_gb, performance)) =
+                    VmSpecs::parse_tina_type(&tina_type)
+                else {
+                    warn!("Cannot extract value from tina type {}", s);
+                    return None;
+                };
 
-                    let category = String::from("Vm");
-                    match digests.get(&category) {
-                        None => {
-                            digests.insert(category, Digest { price: Some(price) });
-                        }
-                        Some(d) => {
-                            digests.insert(
-                                category,
-                                Digest {
-                                    price: Some(d.price.unwrap_or(0.0) + price),
-                                },
-                            );
-                        }
-                    }
-                }
-                s => {
-                    // CustomCore
-                    let Some(catalog_entry) = self.catalog.get(s) else {
-                        warn!("Cannot get catalog entry");
-                        continue;
-                    };
+                // CustomRam
+                let Some(custom_ram_catalog) =
+                    self.catalog.get("TinaOS-FCU/CustomRam/RunInstances-OD")
+                else {
+                    warn!("Cannot get customRam entry");
+                    return None;
+                };
 
-                    let Some(category) = match_entry_id_resource_type(s) else {
-                        warn!("Skip {} because if has no category", s);
-                        continue;
-                    };
+                // CustomCore
+                let Some(custom_core_catalog) = self.catalog.get(&format!(
+                    "TinaOS-FCU/CustomCore:v{generation}-p{performance}/RunInstances-OD",
+                )) else {
+                    warn!("Cannot get customCore entry");
+                    return None;
+                };
 
-                    let price =
-                        entry.value.unwrap_or(0.0) as f32 * catalog_entry.unit_price.unwrap_or(0.0);
+                // value *(CustomRam price * ram + CustomCore price * core)
+                let ram_price = ram_gb
+                    * entry.value.unwrap_or(0.0) as f32
+                    * custom_ram_catalog.unit_price.unwrap_or(0.0);
+                let core_price = vcpu
+                    * entry.value.unwrap_or(0.0) as f32
+                    * custom_core_catalog.unit_price.unwrap_or(0.0);
+                Some((
+                    String::from("Vm"),
+                    vec![
+                        (String::from("Vm/Core"), core_price),
+                        (String::from("Vm/Ram"), ram_price),
+                    ],
+                ))
+            }
+            s => {
+                // CustomCore
+                let Some(catalog_entry) = self.catalog.get(s) else {
+                    warn!("Cannot get catalog entry");
+                    return None;
+                };
 
-                    match digests.get(&category) {
-                        None => {
-                            digests.insert(category, Digest { price: Some(price) });
-                        }
-                        Some(d) => {
-                            digests.insert(
-                                category,
-                                Digest {
-                                    price: Some(d.price.unwrap_or(0.0) + price),
-                                },
-                            );
-                        }
-                    }
-                }
+                let Some(category) = match_entry_id_resource_type(&s.to_string()) else {
+                    warn!("Skip {} because if has no category", s);
+                    return None;
+                };
+
+                let price =
+                    entry.value.unwrap_or(0.0) as f32 * catalog_entry.unit_price.unwrap_or(0.0);
+                let component = match_entry_id_vm_component(s).unwrap_or(category.clone());
+                Some((category, vec![(component, price)]))
             }
         }
     }
 }
+
+// Hourly price of each pricing component of a VM, from its computed prices and the
+// catalog price of its product codes.
+fn vm_components(vm: &Vm, product_price: impl Fn(&str) -> Option<f32>) -> Vec<(String, f32)> {
+    let vcpu = vm.vm_vcpu as f32;
+    let ram_gb = vm.vm_ram_gb as f32;
+    let mut components = vec![
+        (String::from("Vm/Core"), vcpu * vm.price_vcpu_per_hour),
+        (String::from("Vm/Ram"), ram_gb * vm.price_ram_gb_per_hour),
+        (String::from("Vm/Box"), vm.price_box_per_hour),
+    ];
+
+    // The license prices of the VM are the sum of its product codes, split them
+    // according to the catalog price of each code
+    let license = vcpu * vm.price_license_per_cpu_per_hour
+        + ram_gb * vm.price_license_per_ram_gb_per_hour
+        + vm.price_license_per_vm_per_hour;
+    let weights = vm
+        .license_codes
+        .split(',')
+        .filter(|code| !code.is_empty())
+        .map(|code| {
+            let factor =
+                VmSpecs::compute_product_price_per_hour(vcpu, &code.to_string()).unwrap_or(0.0);
+            let price = product_price(code).unwrap_or(0.0);
+            (code, factor * price)
+        })
+        .collect::<Vec<(&str, f32)>>();
+    let total_weight = weights.iter().map(|(_, weight)| weight).sum::<f32>();
+    if total_weight > 0.0 {
+        for (code, weight) in weights {
+            components.push((
+                format!("Vm/License:{code}"),
+                license * weight / total_weight,
+            ));
+        }
+    } else if license > 0.0 {
+        warn!("cannot split the license price of the vm per product code");
+        components.push((String::from("Vm/License"), license));
+    }
+
+    let base = components.iter().map(|(_, price)| price).sum::<f32>();
+    components.push((
+        String::from("Vm/DedicatedSurcharge"),
+        base * (vm.factor_vm_additional_cost - 1.0),
+    ));
+    components
+}
+
+// Replace the consumption with the entries merged per account and catalog id, a period
+// without entries leaves it empty.
+fn fill_consumption(
+    consumption: &mut HashMap<ConsumptionId, ConsumptionEntry>,
+    entries: Option<Vec<ConsumptionEntry>>,
+) {
+    consumption.clear();
+    let Some(entries) = entries else {
+        warn!("no consumption provided");
+        return;
+    };
+
+    for entry in entries {
+        let Some(entry_id) = consumption_entry_id(&entry) else {
+            continue;
+        };
+        let entry_id = (entry.account_id.clone().unwrap_or_default(), entry_id);
+        let entry = match consumption.get(&entry_id) {
+            Some(previous) => merge_entries(previous, entry),
+            None => entry,
+        };
+        consumption.insert(entry_id, entry);
+    }
+}
+
+// Sum of two consumption entries of the same catalog id.
+fn merge_entries(previous: &ConsumptionEntry, entry: ConsumptionEntry) -> ConsumptionEntry {
+    ConsumptionEntry {
+        account_id: entry.account_id,
+        category: entry.category,
+        from_date: entry.from_date,
+        operation: entry.operation,
+        paying_account_id: entry.paying_account_id,
+        resource_id: entry.resource_id,
+        service: entry.service,
+        subregion_name: None,
+        title: entry.title,
+        to_date: entry.to_date,
+        _type: entry._type,
+        value: Some(entry.value.unwrap_or(0.0) + previous.value.unwrap_or(0.0)),
+        // The billed price is only known if it is known for every entry
+        price: entry.price.zip(previous.price).map(|(a, b)| a + b),
+        unit_price: entry.unit_price,
+    }
+}
+
+// Catalog id of a consumption entry, formatted as service/type/operation.
+fn consumption_entry_id(entry: &ConsumptionEntry) -> Option<String> {
+    let Some(service) = &entry.service else {
+        warn!("digest entry has no service");
+        return None;
+    };
+    let Some(operation) = &entry.operation else {
+        warn!("digest entry has no operation");
+        return None;
+    };
+    let Some(_type) = &entry._type else {
+        warn!("digest entry has no type");
+        return None;
+    };
+    Some(format!("{service}/{_type}/{operation}"))
+}
+
+fn add_unmapped(digests: &mut HashMap<String, Digest>, entry: UnmappedEntry) {
+    let digest = digests.entry(String::from(UNMAPPED)).or_default();
+    digest.price = Some(digest.price.unwrap_or(0.0) + entry.price);
+    digest.entries.push(entry);
+    digest.entries.sort_by(|a, b| a.entry_id.cmp(&b.entry_id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(account_id: &str, value: f64, price: f64) -> ConsumptionEntry {
+        ConsumptionEntry {
+            account_id: Some(account_id.to_string()),
+            service: Some(String::from("TinaOS-FCU")),
+            _type: Some(String::from("BoxUsage:tinav5.c2r4p2")),
+            operation: Some(String::from("RunInstances-OD")),
+            value: Some(value),
+            price: Some(price),
+            ..ConsumptionEntry::new()
+        }
+    }
+
+    #[test]
+    fn fill_consumption_merges_entries_per_account() {
+        let mut consumption = HashMap::new();
+        fill_consumption(
+            &mut consumption,
+            Some(vec![
+                entry("123", 2.0, 1.0),
+                entry("123", 3.0, 1.5),
+                entry("456", 1.0, 0.5),
+            ]),
+        );
+
+        assert_eq!(consumption.len(), 2);
+        let id = (
+            String::from("123"),
+            String::from("TinaOS-FCU/BoxUsage:tinav5.c2r4p2/RunInstances-OD"),
+        );
+        assert_eq!(consumption[&id].value, Some(5.0));
+        assert_eq!(consumption[&id].price, Some(2.5));
+    }
+
+    #[test]
+    fn fill_consumption_clears_previous_period() {
+        let mut consumption = HashMap::new();
+        fill_consumption(&mut consumption, Some(vec![entry("123", 2.0, 1.0)]));
+        // A day without consumption must not keep the entries of the previous day
+        fill_consumption(&mut consumption, None);
+        assert!(consumption.is_empty());
+    }
+
+    #[test]
+    fn vm_components_split_the_license_per_product_code() {
+        let vm = Vm {
+            vm_vcpu: 4,
+            vm_ram_gb: 8,
+            price_vcpu_per_hour: 0.1,
+            price_ram_gb_per_hour: 0.05,
+            price_license_per_vm_per_hour: 1.5,
+            factor_vm_additional_cost: 1.1,
+            license_codes: String::from("0002,0004"),
+            ..Default::default()
+        };
+        // Windows is priced per 2 cores, Oracle Linux per VM
+        let product_price = |code: &str| match code {
+            "0002" => Some(0.25),
+            "0004" => Some(0.5),
+            _ => None,
+        };
+        let components = vm_components(&vm, product_price)
+            .into_iter()
+            .collect::<BTreeMap<String, f32>>();
+        let expected = [
+            ("Vm/Core", 0.4),
+            ("Vm/Ram", 0.4),
+            ("Vm/Box", 0.0),
+            ("Vm/License:0002", 0.75),
+            ("Vm/License:0004", 0.75),
+            ("Vm/DedicatedSurcharge", 0.23),
+        ];
+        assert_eq!(components.len(), expected.len());
+        for (component, price) in expected {
+            assert!((components[component] - price).abs() < 1e-5, "{component}");
+        }
+
+        // The components add up to the price of the VM
+        let mut resources = Resources {
+            resources: vec![Resource::Vm(vm)],
+        };
+        resources.compute().unwrap();
+        let total = components.values().sum::<f32>();
+        assert!((total - resources.cost_per_hour().unwrap()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn vm_components_keep_unpriced_licenses_whole() {
+        let vm = Vm {
+            vm_vcpu: 2,
+            price_license_per_cpu_per_hour: 0.2,
+            factor_vm_additional_cost: 1.0,
+            license_codes: String::from("0002"),
+            ..Default::default()
+        };
+        let components = vm_components(&vm, |_| None);
+        assert!(components.contains(&(String::from("Vm/License"), 0.4)));
+        assert!(components.contains(&(String::from("Vm/DedicatedSurcharge"), 0.0)));
+        assert!(!components
+            .iter()
+            .any(|(component, _)| component.starts_with("Vm/License:")));
+    }
+
+    #[test]
+    fn list_cost_applies_the_core_factor() {
+        use aws_config::SdkConfig;
+        use outscale_api::{apis::configuration::Configuration, models::CatalogEntry};
+
+        let mut input = Input::with_config(Configuration::new(), SdkConfig::builder().build());
+        input.catalog.insert(
+            String::from("TinaOS-FCU/ProductUsage/RunInstances-0002-OD"),
+            CatalogEntry {
+                unit_price: Some(0.1),
+                ..Default::default()
+            },
+        );
+        let entry = ConsumptionEntry {
+            unit_price: Some(0.1),
+            value: Some(10.0),
+            ..Default::default()
+        };
+        // Windows is priced per 2 cores
+        let list_cost = input
+            .list_cost(
+                "TinaOS-FCU/ProductUsage:tinav5.c4r8p2/RunInstances-0002-OD",
+                &entry,
+            )
+            .unwrap();
+        assert!((list_cost - 2.0).abs() < 1e-5);
+    }
+}
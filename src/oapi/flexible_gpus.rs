@@ -3,7 +3,7 @@ use std::error;
 use log::{debug, warn};
 use outscale_api::{
     apis::flexible_gpu_api::read_flexible_gpus,
-    models::{ReadFlexibleGpusRequest, ReadFlexibleGpusResponse},
+    models::{ReadFlexibleGpusRequest, ReadFlexibleGpusResponse, ResourceTag},
 };
 
 use crate::{
@@ -11,7 +11,7 @@ use crate::{
     VERSION,
 };
 
-use super::Input;
+use super::{format_tags, Input};
 
 pub type FlexibleGpuId = String;
 
@@ -87,12 +87,25 @@ impl Input {
                 continue;
             }
 
+            // Flexible GPUs use the generic Tag model instead of ResourceTag
+            let tags = flexible_gpu.tags.as_ref().map(|tags| {
+                tags.iter()
+                    .filter_map(|tag| {
+                        Some(ResourceTag::new(
+                            tag.key.clone()?,
+                            tag.value.clone().unwrap_or_default(),
+                        ))
+                    })
+                    .collect()
+            });
+
             let core_flexible_gpu = FlexibleGpu {
                 osc_cost_version: Some(String::from(VERSION)),
                 account_id: self.account_id(),
                 read_date_rfc3339: self.fetch_date.map(|date| date.to_rfc3339()),
                 region: self.region.clone(),
                 resource_id: Some(flexible_gpu_id.clone()),
+                tags: format_tags(&tags),
                 price_per_hour,
                 price_per_month: None,
                 model_name: Some(model_name),
@@ -11,7 +11,7 @@ use crate::{
     VERSION,
 };
 
-use super::Input;
+use super::{format_tags, Input};
 
 pub type LoadbalancerId = String;
 const RESOURCE_NAME: &str = "LoadBalancer";
@@ -41,7 +41,7 @@ impl Input {
                 .load_balancer_name
                 .clone()
                 .unwrap_or_else(|| String::from(""));
-            self.load_balancers.push(lbu_id);
+            self.load_balancers.insert(lbu_id, lbu);
         }
         warn!("info: fetched {} load balancers", self.load_balancers.len());
         Ok(())
@@ -64,13 +64,14 @@ impl Input {
             warn!("warning: could not retrieve the catalog for load balancer");
             return;
         };
-        for resource_id in &self.load_balancers {
+        for (resource_id, lbu) in &self.load_balancers {
             let core_resource = LoadBalancer {
                 osc_cost_version: Some(String::from(VERSION)),
                 account_id: self.account_id(),
                 read_date_rfc3339: self.fetch_date.map(|date| date.to_rfc3339()),
                 region: self.region.clone(),
                 resource_id: Some(resource_id.clone()),
+                tags: format_tags(&lbu.tags),
                 price_per_hour: Some(price_per_hour),
                 price_per_month: None,
             };
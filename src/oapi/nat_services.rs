@@ -11,7 +11,7 @@ use crate::{
     VERSION,
 };
 
-use super::Input;
+use super::{format_tags, Input};
 
 pub type NatServiceId = String;
 const RESOURCE_NAME: &str = "NatServices";
@@ -81,6 +81,7 @@ impl Input {
                 read_date_rfc3339: self.fetch_date.map(|date| date.to_rfc3339()),
                 region: self.region.clone(),
                 resource_id: Some(nat_service_id.clone()),
+                tags: format_tags(&nat_service.tags),
                 price_per_hour: None,
                 price_per_month: None,
                 price_product_per_nat_service_per_hour,
@@ -28,6 +28,7 @@ impl Input {
         // TODO: handle throttling
         let Ok(result) = req.send().await else {
             warn!("warning: error while retrieving the buckets");
+            self.oos_fetch_failed = true;
             return None;
         };
 
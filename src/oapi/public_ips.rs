@@ -11,7 +11,7 @@ use crate::{
     VERSION,
 };
 
-use super::Input;
+use super::{format_tags, Input};
 
 pub type PublicIpId = String;
 const RESOURCE_NAME: &str = "PublicIp";
@@ -132,6 +132,7 @@ impl Input {
                 read_date_rfc3339: self.fetch_date.map(|date| date.to_rfc3339()),
                 region: self.region.clone(),
                 resource_id: Some(public_ip_id.clone()),
+                tags: format_tags(&public_ip.tags),
                 price_per_hour: None,
                 price_per_month: None,
                 price_non_attached,
@@ -11,7 +11,7 @@ use crate::{
     VERSION,
 };
 
-use super::Input;
+use super::{format_tags, Input};
 
 pub type SnapshotId = String;
 const RESOURCE_NAME: &str = "Snapshot";
@@ -89,6 +89,7 @@ impl Input {
                 read_date_rfc3339: self.fetch_date.map(|date| date.to_rfc3339()),
                 region: self.region.clone(),
                 resource_id: Some(snapshot_id.clone()),
+                tags: format_tags(&snapshot.tags),
                 price_per_hour: None,
                 price_per_month: None,
                 volume_size_gib: snapshot.volume_size,
@@ -23,7 +23,7 @@ use crate::{
 use lazy_static::lazy_static;
 use regex::Regex;
 
-use super::Input;
+use super::{format_tags, Input};
 
 pub type VmId = String;
 const RESOURCE_NAME: &str = "Vm";
@@ -217,6 +217,7 @@ impl Input {
                 read_date_rfc3339: self.fetch_date.map(|date| date.to_rfc3339()),
                 region: self.region.clone(),
                 resource_id: Some(vm_id.clone()),
+                tags: format_tags(&vm.tags),
                 price_per_hour: None,
                 price_per_month: None,
                 vm_type: vm.vm_type.clone(),
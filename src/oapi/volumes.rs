@@ -14,7 +14,7 @@ use crate::{
     VERSION,
 };
 
-use super::Input;
+use super::{format_tags, Input};
 
 impl Input {
     pub fn fetch_volumes(&mut self) -> Result<(), Box<dyn error::Error>> {
@@ -75,6 +75,7 @@ impl Input {
                 read_date_rfc3339: self.fetch_date.map(|date| date.to_rfc3339()),
                 region: self.region.clone(),
                 resource_id: Some(volume_id.clone()),
+                tags: format_tags(&volume.tags),
                 price_per_hour: None,
                 price_per_month: None,
                 volume_type: Some(specs.volume_type.clone()),
@@ -11,7 +11,7 @@ use crate::{
     VERSION,
 };
 
-use super::Input;
+use super::{format_tags, Input};
 
 pub type VpnId = String;
 const RESOURCE_NAME: &str = "Vpn";
@@ -51,7 +51,7 @@ impl Input {
                 .vpn_connection_id
                 .clone()
                 .unwrap_or_else(|| String::from(""));
-            self.vpns.push(vpn_id);
+            self.vpns.insert(vpn_id, vpn);
         }
         warn!("info: fetched {} vpns", self.vpns.len());
         Ok(())
@@ -72,13 +72,14 @@ impl Input {
             warn!("warning: could not retrieve the catalog for vpn");
             return;
         };
-        for resource_id in &self.vpns {
+        for (resource_id, vpn) in &self.vpns {
             let core_resource = Vpn {
                 osc_cost_version: Some(String::from(VERSION)),
                 account_id: self.account_id(),
                 read_date_rfc3339: self.fetch_date.map(|date| date.to_rfc3339()),
                 region: self.region.clone(),
                 resource_id: Some(resource_id.clone()),
+                tags: format_tags(&vpn.tags),
                 price_per_hour: Some(price_per_hour),
                 price_per_month: None,
             };
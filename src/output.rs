@@ -1,14 +1,14 @@
+pub mod csv;
+pub mod es;
+pub mod focus;
+pub mod html;
 pub mod human;
+pub mod influx;
 pub mod json;
 pub mod markdown;
 pub mod ods;
+pub mod parquet;
 pub mod prometheus;
+pub mod xlsx;
 
-fn get_currency(region: &str) -> String {
-    match region {
-        "eu-west-2" | "cloudgouv-eu-west-1" => String::from("€"),
-        "ap-northeast-1" => String::from("¥"),
-        "us-east-2" | "us-west-1" => String::from("$"),
-        _ => String::from("€"),
-    }
-}
+pub use osc_cost::core::currency::get_currency;
@@ -0,0 +1,190 @@
+use std::error::Error;
+
+use osc_cost::core::{
+    dedicated_instances::DedicatedInstance, flexible_gpus::FlexibleGpu,
+    load_balancers::LoadBalancer, nat_services::NatServices, oos::Oos, public_ips::PublicIp,
+    snapshots::Snapshot, vms::Vm, volumes::Volume, vpn::Vpn, Aggregate, Resource, Resources,
+};
+use serde_json::Value;
+
+// Fields shared by every resource type, written first
+const COMMON_COLUMNS: [&str; 9] = [
+    "resource_type",
+    "osc_cost_version",
+    "account_id",
+    "read_date_rfc3339",
+    "region",
+    "resource_id",
+    "tags",
+    "price_per_hour",
+    "price_per_month",
+];
+
+// One row per resource. The columns do not depend on the resources: common fields, then
+// the fields of each resource type, or the aggregate fields when all resources are
+// aggregated.
+pub fn csv(resources: &Resources) -> Result<Vec<u8>, Box<dyn Error>> {
+    let columns = columns(aggregated(resources))?
+        .into_iter()
+        .map(|(column, _)| column)
+        .collect::<Vec<String>>();
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(&columns)?;
+    for resource in &resources.resources {
+        let fields = fields(resource)?;
+        writer.write_record(columns.iter().map(|column| {
+            fields
+                .iter()
+                .find(|(key, _)| key == column)
+                .map(|(_, value)| text(value))
+                .unwrap_or_default()
+        }))?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+pub fn aggregated(resources: &Resources) -> bool {
+    !resources.resources.is_empty()
+        && resources
+            .resources
+            .iter()
+            .all(|resource| matches!(resource, Resource::Aggregate(_)))
+}
+
+// Stable column list, with the default value of the first resource type having the column.
+pub fn columns(aggregated: bool) -> Result<Vec<(String, Value)>, Box<dyn Error>> {
+    let templates = match aggregated {
+        true => vec![Resource::Aggregate(Aggregate::default())],
+        false => vec![
+            Resource::Vm(Vm::default()),
+            Resource::Volume(Volume::default()),
+            Resource::PublicIp(PublicIp::default()),
+            Resource::Snapshot(Snapshot::default()),
+            Resource::NatServices(NatServices::default()),
+            Resource::FlexibleGpu(FlexibleGpu::default()),
+            Resource::LoadBalancer(LoadBalancer::default()),
+            Resource::Vpn(Vpn::default()),
+            Resource::Oos(Oos::default()),
+            Resource::DedicatedInstance(DedicatedInstance::default()),
+        ],
+    };
+
+    let mut columns = Vec::<(String, Value)>::new();
+    for template in &templates {
+        for (column, value) in fields(template)? {
+            if !columns.iter().any(|(c, _)| c == &column) {
+                columns.push((column, value));
+            }
+        }
+    }
+    let mut ordered = COMMON_COLUMNS
+        .iter()
+        .filter(|column| !aggregated || (**column != "resource_id" && **column != "tags"))
+        .map(|column| {
+            let value = columns
+                .iter()
+                .find(|(c, _)| c == column)
+                .map(|(_, value)| value.clone())
+                .unwrap_or_default();
+            (column.to_string(), value)
+        })
+        .collect::<Vec<(String, Value)>>();
+    ordered.extend(
+        columns
+            .into_iter()
+            .filter(|(column, _)| !COMMON_COLUMNS.contains(&column.as_str())),
+    );
+    Ok(ordered)
+}
+
+// Serialized fields of a resource, in declaration order.
+pub fn fields(resource: &Resource) -> Result<Vec<(String, Value)>, Box<dyn Error>> {
+    let Value::Object(object) = serde_json::to_value(resource)? else {
+        return Err("a resource must serialize as an object".into());
+    };
+    Ok(object.into_iter().collect())
+}
+
+pub fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(value) => value.clone(),
+        // Prices are f32, print them without the noise of the f64 conversion
+        Value::Number(number) if number.is_f64() => {
+            let value = number.as_f64().unwrap_or_default();
+            match value as f32 as f64 == value {
+                true => (value as f32).to_string(),
+                false => value.to_string(),
+            }
+        }
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    fn header(csv: &[u8]) -> String {
+        String::from_utf8_lossy(csv)
+            .lines()
+            .next()
+            .unwrap()
+            .to_string()
+    }
+
+    fn names(aggregated: bool) -> Vec<String> {
+        columns(aggregated)
+            .unwrap()
+            .into_iter()
+            .map(|(column, _)| column)
+            .collect()
+    }
+
+    #[test]
+    fn columns_are_common_then_per_type() {
+        let columns = names(false);
+        assert_eq!(columns[..COMMON_COLUMNS.len()], COMMON_COLUMNS);
+        for column in ["vm_type", "volume_iops", "size_gb"] {
+            assert!(columns.iter().any(|c| c == column), "{column}");
+        }
+        let unique = columns.iter().collect::<BTreeSet<_>>();
+        assert_eq!(unique.len(), columns.len());
+
+        let aggregated = names(true);
+        assert!(!aggregated.iter().any(|c| c == "resource_id" || c == "tags"));
+        assert!(aggregated.iter().any(|c| c == "aggregated_resource_type"));
+    }
+
+    #[test]
+    fn columns_do_not_depend_on_the_resources() {
+        let empty = csv(&Resources { resources: vec![] }).unwrap();
+        let volume = Volume {
+            resource_id: Some(String::from("vol-1")),
+            tags: Some([("env".to_string(), "a,b=c".to_string())].into()),
+            price_per_hour: Some(0.5),
+            ..Default::default()
+        };
+        let volumes = csv(&Resources {
+            resources: vec![Resource::Volume(volume)],
+        })
+        .unwrap();
+        assert_eq!(header(&empty), header(&volumes));
+
+        let mut reader = csv::Reader::from_reader(volumes.as_slice());
+        let headers = reader.headers().unwrap().clone();
+        let row = reader.records().next().unwrap().unwrap();
+        let value = |column: &str| {
+            let index = headers.iter().position(|h| h == column).unwrap();
+            row[index].to_string()
+        };
+        assert_eq!(value("resource_type"), "Volume");
+        assert_eq!(value("resource_id"), "vol-1");
+        assert_eq!(value("tags"), r#"{"env":"a,b=c"}"#);
+        assert_eq!(value("price_per_hour"), "0.5");
+        assert_eq!(value("vm_type"), "");
+    }
+}
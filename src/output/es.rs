@@ -0,0 +1,137 @@
+use std::error::Error;
+use std::fmt::Write;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::info;
+use osc_cost::core::Resources;
+use reqwest::blocking::Client;
+use serde_json::{json, Value};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// Elasticsearch/OpenSearch bulk request: an index action line then the resource document,
+// with an @timestamp from its read date. The index name is the strftime pattern applied
+// to the read date, e.g. osc-cost-%Y.%m.%d.
+pub fn es_bulk(resources: &Resources, index_pattern: &str) -> Result<String, Box<dyn Error>> {
+    let now = Utc::now();
+    let mut out = String::new();
+    for resource in &resources.resources {
+        let read_date = resource
+            .read_date()
+            .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+            .map(|date| date.with_timezone(&Utc));
+        let timestamp = read_date.unwrap_or(now);
+
+        let mut index = String::new();
+        write!(index, "{}", timestamp.format(index_pattern))
+            .map_err(|e| format!("invalid index pattern {index_pattern}: {e}"))?;
+        let mut action = json!({ "_index": index });
+        // Indexing the same inventory twice overwrites the documents
+        if let Some(read_date) = read_date {
+            action["_id"] = Value::from(format!(
+                "{}-{}-{}",
+                resource.account_id().unwrap_or_default(),
+                resource.resource_id().unwrap_or(resource.resource_type()),
+                read_date.timestamp()
+            ));
+        }
+
+        let mut document = serde_json::to_value(resource)?;
+        document["@timestamp"] = Value::from(timestamp.to_rfc3339());
+        out.push_str(&json!({ "index": action }).to_string());
+        out.push('\n');
+        out.push_str(&document.to_string());
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+// Send the bulk request to <URL>/_bulk, with the extra "Name: value" headers.
+pub fn post_bulk(url: &str, headers: &[String], body: String) -> Result<(), Box<dyn Error>> {
+    let endpoint = format!("{}/_bulk", url.trim_end_matches('/'));
+    let client = Client::builder().timeout(TIMEOUT).build()?;
+    let mut request = client
+        .post(&endpoint)
+        .header("Content-Type", "application/x-ndjson")
+        .body(body);
+    for header in headers {
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(format!("invalid header {header}, expected \"Name: value\"").into());
+        };
+        request = request.header(name.trim(), value.trim());
+    }
+
+    let response = request.send()?;
+    let status = response.status();
+    let response = response.text()?;
+    if !status.is_success() {
+        return Err(format!("cannot index to {endpoint}: {status} {response}").into());
+    }
+    // The request succeeds even when some documents are rejected
+    let response: Value = serde_json::from_str(&response)?;
+    let items = response["items"]
+        .as_array()
+        .map(Vec::len)
+        .unwrap_or_default();
+    if response["errors"].as_bool().unwrap_or_default() {
+        let failed = response["items"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|item| !item["index"]["error"].is_null())
+            .count();
+        return Err(format!("{failed} of {items} documents were rejected by {endpoint}").into());
+    }
+    info!("indexed {items} documents to {endpoint}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use osc_cost::core::{volumes::Volume, Resource};
+
+    use super::*;
+
+    fn volume(resource_id: &str, read_date: Option<&str>) -> Resource {
+        Resource::Volume(Volume {
+            account_id: Some(String::from("123")),
+            resource_id: Some(resource_id.to_string()),
+            read_date_rfc3339: read_date.map(String::from),
+            price_per_hour: Some(0.5),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn bulk_body_has_an_action_per_document() {
+        let resources = Resources {
+            resources: vec![
+                volume("vol-1", Some("2024-04-01T10:00:00+02:00")),
+                volume("vol-2", None),
+            ],
+        };
+        let bulk = es_bulk(&resources, "osc-cost-%Y.%m.%d").unwrap();
+        assert!(bulk.ends_with('\n'));
+        let lines = bulk
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<Value>>();
+        assert_eq!(lines.len(), 4);
+
+        assert_eq!(
+            lines[0],
+            json!({ "index": { "_index": "osc-cost-2024.04.01", "_id": "123-vol-1-1711958400" } })
+        );
+        assert_eq!(lines[1]["resource_id"], "vol-1");
+        assert_eq!(lines[1]["@timestamp"], "2024-04-01T08:00:00+00:00");
+
+        // Without read date, the document is indexed at the current time without id
+        assert!(lines[2]["index"]["_id"].is_null());
+        assert!(lines[2]["index"]["_index"]
+            .as_str()
+            .unwrap()
+            .starts_with("osc-cost-"));
+        assert_eq!(lines[3]["resource_id"], "vol-2");
+    }
+}
@@ -0,0 +1,154 @@
+use std::error::Error;
+
+use osc_cost::core::{currency::get_currency_code, focus::Charges};
+
+const COLUMNS: [&str; 27] = [
+    "BillingAccountId",
+    "SubAccountId",
+    "BillingCurrency",
+    "BillingPeriodStart",
+    "BillingPeriodEnd",
+    "ChargePeriodStart",
+    "ChargePeriodEnd",
+    "ChargeCategory",
+    "ChargeDescription",
+    "ProviderName",
+    "PublisherName",
+    "InvoiceIssuerName",
+    "RegionId",
+    "ResourceId",
+    "ResourceType",
+    "ServiceName",
+    "ServiceCategory",
+    "SkuId",
+    "ListUnitPrice",
+    "PricingQuantity",
+    "PricingUnit",
+    "ListCost",
+    "EffectiveCost",
+    "BilledCost",
+    "ContractedCost",
+    "Tags",
+    // Estimate or Consumption, the estimated rows are not billed
+    "x_CostSource",
+];
+
+// FinOps Open Cost and Usage Specification (FOCUS) rows as CSV.
+pub fn focus(charges: &Charges) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(COLUMNS)?;
+    for charge in &charges.charges {
+        let currency = get_currency_code(charge.region_id.as_deref().unwrap_or_default());
+        let optional =
+            |value: Option<f32>| value.map(|value| value.to_string()).unwrap_or_default();
+        writer.write_record([
+            charge.billing_account_id.clone().unwrap_or_default(),
+            charge.sub_account_id.clone().unwrap_or_default(),
+            currency.to_string(),
+            charge.charge_period_start.clone(),
+            charge.charge_period_end.clone(),
+            charge.charge_period_start.clone(),
+            charge.charge_period_end.clone(),
+            String::from("Usage"),
+            charge.charge_description.clone().unwrap_or_default(),
+            String::from("OUTSCALE"),
+            String::from("OUTSCALE"),
+            String::from("OUTSCALE"),
+            charge.region_id.clone().unwrap_or_default(),
+            charge.resource_id.clone().unwrap_or_default(),
+            charge.resource_type.clone().unwrap_or_default(),
+            charge.service_name.clone(),
+            charge.service_category.clone(),
+            charge.sku_id.clone().unwrap_or_default(),
+            optional(charge.list_unit_price),
+            charge.pricing_quantity.to_string(),
+            charge.pricing_unit.clone().unwrap_or_default(),
+            charge.list_cost.to_string(),
+            charge.effective_cost.to_string(),
+            charge.billed_cost.to_string(),
+            charge.effective_cost.to_string(),
+            // FOCUS tags are a JSON object
+            match &charge.tags {
+                Some(tags) => serde_json::to_string(tags)?,
+                None => String::new(),
+            },
+            charge.source.clone(),
+        ])?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use osc_cost::core::{
+        focus::{estimate_charges, Charge, CONSUMPTION, ESTIMATE},
+        volumes::Volume,
+        Resource, Resources, Tags,
+    };
+
+    use super::*;
+
+    #[test]
+    fn estimate_and_consumption_rows() {
+        let resources = Resources {
+            resources: vec![Resource::Volume(Volume {
+                account_id: Some(String::from("123")),
+                region: Some(String::from("us-east-2")),
+                resource_id: Some(String::from("vol-1")),
+                tags: Some(Tags::from([(String::from("env"), String::from("prod"))])),
+                price_per_hour: Some(0.5),
+                ..Default::default()
+            })],
+        };
+        let mut charges = estimate_charges(&resources, "2024-04-01", "2024-04-02").unwrap();
+        charges.push(Charge {
+            source: CONSUMPTION.to_string(),
+            sub_account_id: Some(String::from("123")),
+            region_id: Some(String::from("eu-west-2")),
+            resource_id: Some(String::from("vol-1")),
+            sku_id: Some(String::from("TinaOS-FCU/BSU:VolumeUsage:gp2/CreateVolume")),
+            pricing_quantity: 24.0,
+            list_cost: 11.0,
+            effective_cost: 12.0,
+            billed_cost: 12.0,
+            ..Default::default()
+        });
+        let focus = focus(&Charges { charges }).unwrap();
+
+        let mut reader = csv::Reader::from_reader(focus.as_slice());
+        assert_eq!(reader.headers().unwrap(), COLUMNS.as_slice());
+        let rows = reader
+            .records()
+            .map(|row| {
+                let row = row.unwrap();
+                COLUMNS
+                    .iter()
+                    .zip(row.iter())
+                    .map(|(column, value)| (*column, value.to_string()))
+                    .collect::<BTreeMap<&str, String>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 2);
+
+        let estimate = &rows[0];
+        assert_eq!(estimate["x_CostSource"], ESTIMATE);
+        assert_eq!(estimate["BillingCurrency"], "USD");
+        assert_eq!(estimate["ResourceId"], "vol-1");
+        assert_eq!(estimate["ServiceCategory"], "Storage");
+        assert_eq!(estimate["ChargePeriodStart"], "2024-04-01T00:00:00Z");
+        assert_eq!(estimate["PricingQuantity"], "24");
+        assert_eq!(estimate["ListCost"], "12");
+        assert_eq!(estimate["BilledCost"], "0");
+        assert_eq!(estimate["Tags"], r#"{"env":"prod"}"#);
+
+        let consumption = &rows[1];
+        assert_eq!(consumption["x_CostSource"], CONSUMPTION);
+        assert_eq!(consumption["BillingCurrency"], "EUR");
+        assert_eq!(consumption["ListCost"], "11");
+        assert_eq!(consumption["BilledCost"], "12");
+        assert_eq!(consumption["ListUnitPrice"], "");
+        assert_eq!(consumption["Tags"], "");
+    }
+}
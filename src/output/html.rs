@@ -0,0 +1,367 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use chrono::{SecondsFormat, Utc};
+use osc_cost::core::{price_per_year, Resource, Resources};
+
+use super::get_currency;
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222;max-width:1100px}\
+table{border-collapse:collapse;margin-bottom:2em}\
+th,td{border:1px solid #ccc;padding:4px 10px;text-align:left}\
+th{background:#f0f0f0}td.num{text-align:right}\
+table.sortable th{cursor:pointer}table.sortable th:after{content:' \\2195';color:#999}\
+.charts{display:flex;gap:3em;align-items:center;margin-bottom:2em}\
+.legend span{display:inline-block;width:12px;height:12px;margin-right:6px}";
+
+// Sort the detail table when a header is clicked, numbers are compared as numbers
+const SCRIPT: &str = "document.querySelectorAll('table.sortable').forEach(function(t){\
+t.querySelectorAll('th').forEach(function(th,i){var asc=true;th.onclick=function(){\
+var b=t.tBodies[0],rows=Array.from(b.rows);rows.sort(function(x,y){\
+var u=x.cells[i].dataset.value||x.cells[i].textContent,v=y.cells[i].dataset.value||y.cells[i].textContent,\
+p=parseFloat(u),q=parseFloat(v),c=isNaN(p)||isNaN(q)?u.localeCompare(v):p-q;return asc?c:-c});\
+asc=!asc;rows.forEach(function(r){b.appendChild(r)})}})});";
+
+const COLORS: [&str; 10] = [
+    "#4a90d9", "#e67e22", "#2ecc71", "#9b59b6", "#e74c3c", "#1abc9c", "#f1c40f", "#34495e",
+    "#95a5a6", "#d35400",
+];
+
+#[derive(Default)]
+struct TypeTotal<'a> {
+    count: usize,
+    price_per_hour: f32,
+    price_per_month: f32,
+    resources: Vec<(f32, &'a Resource)>,
+}
+
+pub struct Options<'a> {
+    pub title: &'a str,
+    // Reload period of the page in seconds
+    pub refresh: Option<u64>,
+    // List the N most expensive resources instead of every resource per type
+    pub top: Option<usize>,
+}
+
+impl Default for Options<'_> {
+    fn default() -> Self {
+        Self {
+            title: "osc-cost report",
+            refresh: None,
+            top: None,
+        }
+    }
+}
+
+// Standalone report, styles, charts and script are inlined so it can be mailed. The
+// exporter dashboard uses the same report, reloading itself and listing the top resources.
+pub fn html(resources: &Resources, options: &Options) -> Result<String, Box<dyn Error>> {
+    let first = resources.resources.first();
+    let region = first.and_then(|r| r.region()).unwrap_or_default();
+    let account_id = first.and_then(|r| r.account_id()).unwrap_or_default();
+    let currency = get_currency(region);
+
+    let mut types = BTreeMap::<&str, TypeTotal>::new();
+    for resource in &resources.resources {
+        let price_per_hour = resource.price_per_hour()?;
+        let total = types.entry(resource.resource_type()).or_default();
+        total.count += match resource {
+            Resource::Aggregate(aggregate) => aggregate.count as usize,
+            _ => 1,
+        };
+        total.price_per_hour += price_per_hour;
+        total.price_per_month += resource.price_per_month().unwrap_or_default();
+        total.resources.push((price_per_hour, resource));
+    }
+    for total in types.values_mut() {
+        total.resources.sort_by(|a, b| b.0.total_cmp(&a.0));
+    }
+
+    let cost_per_hour = resources.cost_per_hour()?;
+    let cost_per_month = resources.cost_per_month()?;
+    let cost_per_year = resources.cost_per_year()?;
+    let share = |price_per_hour: f32| match cost_per_hour > 0.0 {
+        true => price_per_hour * 100.0 / cost_per_hour,
+        false => 0.0,
+    };
+
+    let title = escape(options.title);
+    let refresh = options
+        .refresh
+        .map(|refresh| format!("<meta http-equiv=\"refresh\" content=\"{refresh}\">"))
+        .unwrap_or_default();
+    let mut out = String::new();
+    out.push_str(&format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">{refresh}\
+         <title>{title}</title><style>{STYLE}</style></head><body>\
+         <h1>{title}</h1><p>Generated at {}</p>",
+        Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+    ));
+
+    out.push_str("<h2>Summary</h2><table>");
+    out.push_str(&format!(
+        "<tr><th>Account Id</th><td>{}</td></tr><tr><th>Region</th><td>{}</td></tr>\
+         <tr><th>Total price per hour</th><td class=\"num\">{cost_per_hour:.2}{currency}</td></tr>\
+         <tr><th>Total price per month</th><td class=\"num\">{cost_per_month:.2}{currency}</td></tr>\
+         <tr><th>Total price per year</th><td class=\"num\">{cost_per_year:.2}{currency}</td></tr>",
+        escape(account_id),
+        escape(region),
+    ));
+    out.push_str("</table>");
+
+    // Donut and bars of the cost share per resource type
+    out.push_str("<h2>Cost share</h2><div class=\"charts\">");
+    out.push_str(
+        "<svg width=\"220\" height=\"220\" viewBox=\"0 0 42 42\">\
+         <circle cx=\"21\" cy=\"21\" r=\"15.915\" fill=\"none\" stroke=\"#eee\" stroke-width=\"6\"/>",
+    );
+    // The circumference is 100 so that the dash lengths are percentages
+    let mut offset = 0.0;
+    for (index, total) in types.values().enumerate() {
+        let share = share(total.price_per_hour);
+        out.push_str(&format!(
+            "<circle cx=\"21\" cy=\"21\" r=\"15.915\" fill=\"none\" stroke=\"{}\" \
+             stroke-width=\"6\" stroke-dasharray=\"{share:.3} {:.3}\" \
+             stroke-dashoffset=\"{:.3}\"/>",
+            color(index),
+            100.0 - share,
+            25.0 - offset,
+        ));
+        offset += share;
+    }
+    out.push_str("</svg>");
+
+    let height = types.len() * 24;
+    out.push_str(&format!(
+        "<svg width=\"520\" height=\"{height}\" viewBox=\"0 0 520 {height}\">"
+    ));
+    for (index, (resource_type, total)) in types.iter().enumerate() {
+        let share = share(total.price_per_hour);
+        let y = index * 24;
+        out.push_str(&format!(
+            "<text x=\"0\" y=\"{}\" font-size=\"12\">{}</text>\
+             <rect x=\"120\" y=\"{}\" width=\"{:.1}\" height=\"16\" fill=\"{}\"/>\
+             <text x=\"{:.1}\" y=\"{}\" font-size=\"12\">{share:.1}%</text>",
+            y + 14,
+            escape(resource_type),
+            y + 2,
+            share * 3.2,
+            color(index),
+            126.0 + share * 3.2,
+            y + 14,
+        ));
+    }
+    out.push_str("</svg></div>");
+
+    out.push_str(
+        "<h2>Resource types</h2><table><tr><th>Resource Type</th><th>Count</th>\
+         <th>Total price per hour</th><th>Total price per month</th>\
+         <th>Total price per year</th><th>Share</th></tr>",
+    );
+    for (index, (resource_type, total)) in types.iter().enumerate() {
+        out.push_str(&format!(
+            "<tr><td class=\"legend\"><span style=\"background:{}\"></span>{}</td>\
+             <td class=\"num\">{}</td><td class=\"num\">{:.2}{currency}</td>\
+             <td class=\"num\">{:.2}{currency}</td><td class=\"num\">{:.2}{currency}</td>\
+             <td class=\"num\">{:.1}%</td></tr>",
+            color(index),
+            escape(resource_type),
+            total.count,
+            total.price_per_hour,
+            total.price_per_month,
+            price_per_year(total.price_per_hour),
+            share(total.price_per_hour),
+        ));
+    }
+    out.push_str("</table>");
+
+    if let Some(top) = options.top {
+        let mut priced = types
+            .values()
+            .flat_map(|total| total.resources.iter())
+            .collect::<Vec<_>>();
+        priced.sort_by(|a, b| b.0.total_cmp(&a.0));
+        out.push_str(&format!(
+            "<h2>Top {top} most expensive resources</h2><table><tr><th>Resource Type</th>\
+             <th>Resource Id</th><th>Tags</th><th>Price per hour</th><th>Price per month</th></tr>"
+        ));
+        for (price_per_hour, resource) in priced.into_iter().take(top) {
+            out.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td>\
+                 <td class=\"num\">{price_per_hour:.4}{currency}</td>\
+                 <td class=\"num\">{:.2}{currency}</td></tr>",
+                escape(resource.resource_type()),
+                escape(resource.resource_id().unwrap_or_default()),
+                escape(&tags(resource)),
+                resource.price_per_month().unwrap_or_default(),
+            ));
+        }
+        out.push_str("</table></body></html>\n");
+        return Ok(out);
+    }
+
+    for (resource_type, total) in &types {
+        out.push_str(&format!(
+            "<h3>{}</h3><table><tr><th>Resource Id</th><th>Tags</th>\
+             <th>Price per hour</th><th>Price per month</th></tr>",
+            escape(resource_type)
+        ));
+        for (price_per_hour, resource) in &total.resources {
+            out.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td class=\"num\">{price_per_hour:.4}{currency}</td>\
+                 <td class=\"num\">{:.2}{currency}</td></tr>",
+                escape(resource.resource_id().unwrap_or_default()),
+                escape(&tags(resource)),
+                resource.price_per_month().unwrap_or_default(),
+            ));
+        }
+        out.push_str("</table>");
+    }
+
+    out.push_str(
+        "<h2>Details</h2><table class=\"sortable\"><thead><tr><th>Resource Type</th>\
+         <th>Resource Id</th><th>Tags</th><th>Price per hour</th><th>Price per month</th>\
+         </tr></thead><tbody>",
+    );
+    for total in types.values() {
+        for (price_per_hour, resource) in &total.resources {
+            let price_per_month = resource.price_per_month().unwrap_or_default();
+            out.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td>\
+                 <td class=\"num\" data-value=\"{price_per_hour}\">{price_per_hour:.4}{currency}</td>\
+                 <td class=\"num\" data-value=\"{price_per_month}\">{price_per_month:.2}{currency}</td>\
+                 </tr>",
+                escape(resource.resource_type()),
+                escape(resource.resource_id().unwrap_or_default()),
+                escape(&tags(resource)),
+            ));
+        }
+    }
+    out.push_str(&format!(
+        "</tbody></table><script>{SCRIPT}</script></body></html>\n"
+    ));
+
+    Ok(out)
+}
+
+// Tags of a resource as "key=value" pairs.
+fn tags(resource: &Resource) -> String {
+    resource
+        .tags()
+        .into_iter()
+        .flatten()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn color(index: usize) -> &'static str {
+    COLORS.get(index % COLORS.len()).copied().unwrap_or("#999")
+}
+
+pub fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use osc_cost::core::{vms::Vm, volumes::Volume};
+
+    use super::*;
+
+    fn resources() -> Resources {
+        let volume = |resource_id: &str, price_per_hour| {
+            Resource::Volume(Volume {
+                region: Some(String::from("eu-west-2")),
+                resource_id: Some(resource_id.to_string()),
+                price_per_hour: Some(price_per_hour),
+                ..Default::default()
+            })
+        };
+        let mut resources = Resources {
+            resources: vec![
+                Resource::Vm(Vm {
+                    region: Some(String::from("eu-west-2")),
+                    resource_id: Some(String::from("i-<1>")),
+                    vm_vcpu: 2,
+                    vm_ram_gb: 4,
+                    price_vcpu_per_hour: 0.1,
+                    price_ram_gb_per_hour: 0.05,
+                    // Dedicated VM, its month price is computed before the factor
+                    factor_vm_additional_cost: 1.1,
+                    ..Default::default()
+                }),
+                volume("vol-1", 0.5),
+                volume("vol-2", 0.25),
+            ],
+        };
+        resources.compute().unwrap();
+        resources
+    }
+
+    // Count, hour, month and year cells of a resource type row
+    fn type_row(resources: &Resources, resource_type: &str) -> String {
+        let of_type = resources
+            .resources
+            .iter()
+            .filter(|resource| resource.resource_type() == resource_type)
+            .collect::<Vec<_>>();
+        let price_per_hour = of_type
+            .iter()
+            .map(|resource| resource.price_per_hour().unwrap())
+            .sum::<f32>();
+        let price_per_month = of_type
+            .iter()
+            .map(|resource| resource.price_per_month().unwrap())
+            .sum::<f32>();
+        format!(
+            "{resource_type}</td><td class=\"num\">{}</td>\
+             <td class=\"num\">{price_per_hour:.2}€</td><td class=\"num\">{price_per_month:.2}€</td>\
+             <td class=\"num\">{:.2}€</td>",
+            of_type.len(),
+            price_per_year(price_per_hour),
+        )
+    }
+
+    #[test]
+    fn report_has_the_type_rows_and_totals() {
+        let resources = resources();
+        let report = html(&resources, &Options::default()).unwrap();
+
+        assert!(report.contains(&type_row(&resources, "Vm")));
+        assert!(report.contains(&type_row(&resources, "Volume")));
+        assert!(report.contains(&format!(
+            "<th>Total price per year</th><td class=\"num\">{:.2}€</td>",
+            resources.cost_per_year().unwrap()
+        )));
+        assert!(report.contains("<h3>Volume</h3>"));
+        assert!(report.contains("i-&lt;1&gt;"));
+        assert!(!report.contains("i-<1>"));
+    }
+
+    #[test]
+    fn top_lists_the_most_expensive_resources() {
+        let options = Options {
+            top: Some(2),
+            refresh: Some(60),
+            ..Default::default()
+        };
+        let report = html(&resources(), &options).unwrap();
+        assert!(report.contains("<meta http-equiv=\"refresh\" content=\"60\">"));
+        assert!(report.contains("<h2>Top 2 most expensive resources</h2>"));
+        assert!(report.contains("vol-1"));
+        assert!(!report.contains("vol-2"));
+        assert!(!report.contains("<h3>"));
+    }
+}
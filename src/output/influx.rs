@@ -0,0 +1,168 @@
+use std::error::Error;
+
+use chrono::DateTime;
+use log::warn;
+use osc_cost::core::{Resource, Resources};
+
+// Tags of every point, resource tags with the same key are not written
+const RESERVED_TAGS: [&str; 3] = ["account_id", "region", "resource_id"];
+
+// One line protocol point per resource: the resource type as measurement, the account,
+// region, resource id and the selected resource tags as tags and the prices as fields.
+pub fn influx(resources: &Resources, tag_keys: &[String]) -> Result<String, Box<dyn Error>> {
+    let tag_keys = tag_keys
+        .iter()
+        .filter(|key| match RESERVED_TAGS.contains(&key.as_str()) {
+            true => {
+                warn!("skipping the resource tag {key}, it is a tag of every point");
+                false
+            }
+            false => true,
+        })
+        .collect::<Vec<&String>>();
+    let mut out = String::new();
+    for resource in &resources.resources {
+        let mut fields = Vec::new();
+        if let Ok(price_per_hour) = resource.price_per_hour() {
+            fields.push(format!("price_per_hour={price_per_hour}"));
+        }
+        if let Some(price_per_month) = resource.price_per_month() {
+            fields.push(format!("price_per_month={price_per_month}"));
+        }
+        if let Resource::Aggregate(aggregate) = resource {
+            fields.push(format!("count={}i", aggregate.count));
+        }
+        if fields.is_empty() {
+            warn!(
+                "skipping {} {}, it has no price",
+                resource.resource_type(),
+                resource.resource_id().unwrap_or_default()
+            );
+            continue;
+        }
+
+        let mut tags = vec![
+            ("account_id", resource.account_id().unwrap_or_default()),
+            ("region", resource.region().unwrap_or_default()),
+            ("resource_id", resource.resource_id().unwrap_or_default()),
+        ];
+        for (key, value) in resource.tags().into_iter().flatten() {
+            if tag_keys.contains(&key) {
+                tags.push((key, value));
+            }
+        }
+        // InfluxDB expects the tags sorted by key, empty values are not allowed
+        tags.sort();
+
+        out.push_str(&escape(resource.resource_type(), &[',', ' ']));
+        for (key, value) in tags.iter().filter(|(_, value)| !value.is_empty()) {
+            out.push_str(&format!(
+                ",{}={}",
+                escape(key, &[',', '=', ' ']),
+                escape(value, &[',', '=', ' '])
+            ));
+        }
+        out.push(' ');
+        out.push_str(&fields.join(","));
+        if let Some(timestamp) = resource
+            .read_date()
+            .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+            .and_then(|date| date.timestamp_nanos_opt())
+        {
+            out.push_str(&format!(" {timestamp}"));
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn escape(value: &str, special: &[char]) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '\\' || special.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use osc_cost::core::{public_ips::PublicIp, volumes::Volume, Tags};
+
+    use super::*;
+
+    fn volume(tags: Tags) -> Resource {
+        Resource::Volume(Volume {
+            account_id: Some(String::from("123")),
+            region: Some(String::from("eu-west-2")),
+            resource_id: Some(String::from("vol-1")),
+            read_date_rfc3339: Some(String::from("2024-04-01T00:00:00Z")),
+            tags: Some(tags),
+            price_per_hour: Some(0.5),
+            price_per_month: Some(365.0),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn points_have_sorted_tags_and_prices() {
+        let tags = Tags::from([
+            (String::from("team"), String::from("data")),
+            (String::from("env"), String::from("prod")),
+            (String::from("owner"), String::from("me")),
+        ]);
+        let resources = Resources {
+            resources: vec![volume(tags)],
+        };
+        let tag_keys = [String::from("team"), String::from("env")];
+        assert_eq!(
+            influx(&resources, &tag_keys).unwrap(),
+            "Volume,account_id=123,env=prod,region=eu-west-2,resource_id=vol-1,team=data \
+             price_per_hour=0.5,price_per_month=365 1711929600000000000\n"
+        );
+    }
+
+    #[test]
+    fn reserved_tag_keys_are_not_duplicated() {
+        let tags = Tags::from([(String::from("region"), String::from("other"))]);
+        let resources = Resources {
+            resources: vec![volume(tags)],
+        };
+        let points = influx(&resources, &[String::from("region")]).unwrap();
+        assert_eq!(points.matches("region=").count(), 1);
+        assert!(points.contains("region=eu-west-2"));
+    }
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert_eq!(escape("a b,c=d\\e", &[',', '=', ' ']), "a\\ b\\,c\\=d\\\\e");
+        assert_eq!(escape("a b,c=d", &[',', ' ']), "a\\ b\\,c=d");
+
+        let tags = Tags::from([(String::from("env"), String::from("a,b=c d"))]);
+        let resources = Resources {
+            resources: vec![volume(tags)],
+        };
+        let points = influx(&resources, &[String::from("env")]).unwrap();
+        assert!(points.contains(",env=a\\,b\\=c\\ d,"));
+    }
+
+    #[test]
+    fn resources_without_price_are_skipped() {
+        let resources = Resources {
+            resources: vec![
+                // Not computed
+                Resource::PublicIp(PublicIp {
+                    price_per_hour: None,
+                    price_per_month: None,
+                    ..Default::default()
+                }),
+                volume(Tags::new()),
+            ],
+        };
+        let points = influx(&resources, &[]).unwrap();
+        assert_eq!(points.lines().count(), 1);
+        assert!(points.starts_with("Volume,"));
+    }
+}
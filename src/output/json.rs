@@ -1,42 +1,198 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+
+use chrono::{SecondsFormat, Utc};
 use log::warn;
-use osc_cost::core::{digest::Drifts, Resources};
+use osc_cost::core::{
+    digest::{AccountDrifts, DailyDrifts, Drifts, MonthToDate, ResourceDrifts},
+    price_per_year, Resource, Resources,
+};
+use osc_cost::forecast::Forecast;
+use osc_cost::history::CostHistory;
+use serde::Serialize;
 
 pub trait Json {
     fn json(&self) -> serde_json::Result<String>;
 }
 
-impl Json for Resources {
-    fn json(&self) -> serde_json::Result<String> {
-        let mut out = String::new();
-        for resource in &self.resources {
-            match serde_json::to_string(resource) {
-                Ok(serialized) => out.push_str(serialized.as_str()),
-                Err(e) => {
-                    warn!("resource serialization error: {}", e);
-                    continue;
-                }
+// One JSON document per line, items that fail to serialize are skipped with a warning.
+fn ndjson<T: Serialize>(items: &[T], kind: &str) -> String {
+    let mut out = String::new();
+    for item in items {
+        match serde_json::to_string(item) {
+            Ok(serialized) => out.push_str(serialized.as_str()),
+            Err(e) => {
+                warn!("{} serialization error: {}", kind, e);
+                continue;
             }
-            out.push('\n');
         }
-        out.pop();
-        Ok(out)
+        out.push('\n');
+    }
+    out.pop();
+    out
+}
+
+impl Json for Resources {
+    fn json(&self) -> serde_json::Result<String> {
+        Ok(ndjson(&self.resources, "resource"))
     }
 }
 
 impl Json for Drifts {
     fn json(&self) -> serde_json::Result<String> {
-        let mut out = String::new();
-        for drift in &self.drifts {
-            match serde_json::to_string(drift) {
-                Ok(serialized) => out.push_str(serialized.as_str()),
-                Err(e) => {
-                    warn!("drift serialization error: {}", e);
-                    continue;
-                }
-            }
-            out.push('\n');
+        Ok(ndjson(&self.drifts, "drift"))
+    }
+}
+
+impl Json for CostHistory {
+    fn json(&self) -> serde_json::Result<String> {
+        Ok(ndjson(&self.entries, "history"))
+    }
+}
+
+impl Json for Forecast {
+    fn json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+}
+
+impl Json for MonthToDate {
+    fn json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+}
+
+impl Json for DailyDrifts {
+    fn json(&self) -> serde_json::Result<String> {
+        Ok(ndjson(&self.drifts, "drift"))
+    }
+}
+
+impl Json for ResourceDrifts {
+    fn json(&self) -> serde_json::Result<String> {
+        Ok(ndjson(&self.drifts, "drift"))
+    }
+}
+
+impl Json for AccountDrifts {
+    fn json(&self) -> serde_json::Result<String> {
+        Ok(ndjson(&self.drifts, "drift"))
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ReportMetadata {
+    pub osc_cost_version: String,
+    pub generated_at: String,
+    pub accounts: Vec<String>,
+    pub regions: Vec<String>,
+    pub filters: ReportFilters,
+    pub skipped_resource_types: Vec<String>,
+    // The bucket listing failed and Oos is missing, other fetch errors abort the run
+    pub oos_fetch_failed: bool,
+    // Time the catalog was read
+    pub catalog_fetched_at: Option<String>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ReportFilters {
+    pub tag_keys: Vec<String>,
+    pub tag_values: Vec<String>,
+    pub tags: Vec<String>,
+}
+
+impl ReportMetadata {
+    // Version, generation time, accounts and regions of the resources.
+    pub fn new(resources: &Resources) -> Self {
+        let accounts = resources
+            .resources
+            .iter()
+            .filter_map(|resource| resource.account_id())
+            .map(String::from)
+            .collect::<BTreeSet<String>>();
+        let regions = resources
+            .resources
+            .iter()
+            .filter_map(|resource| resource.region())
+            .map(String::from)
+            .collect::<BTreeSet<String>>();
+        ReportMetadata {
+            osc_cost_version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            accounts: accounts.into_iter().collect(),
+            regions: regions.into_iter().collect(),
+            ..Default::default()
         }
-        out.pop();
-        Ok(out)
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+struct TypeTotal {
+    count: i32,
+    price_per_hour: f32,
+    price_per_month: f32,
+    price_per_year: f32,
+}
+
+#[derive(Serialize, Debug)]
+struct Totals {
+    cost_per_hour: f32,
+    cost_per_month: f32,
+    cost_per_year: f32,
+}
+
+#[derive(Serialize, Debug)]
+struct JsonReport<'a> {
+    metadata: ReportMetadata,
+    resources: &'a [Resource],
+    resource_types: BTreeMap<&'a str, TypeTotal>,
+    totals: Totals,
+}
+
+// Single JSON document with the metadata of the run, the resources and their totals per
+// resource type and overall.
+pub fn json_report(
+    resources: &Resources,
+    metadata: ReportMetadata,
+) -> Result<String, Box<dyn Error>> {
+    let mut resource_types = BTreeMap::<&str, TypeTotal>::new();
+    for resource in &resources.resources {
+        let total = resource_types.entry(resource.resource_type()).or_default();
+        total.count += match resource {
+            Resource::Aggregate(aggregate) => aggregate.count,
+            _ => 1,
+        };
+        total.price_per_hour += resource.price_per_hour()?;
+        total.price_per_month += resource.price_per_month().unwrap_or_default();
+        total.price_per_year = price_per_year(total.price_per_hour);
+    }
+
+    Ok(serde_json::to_string(&JsonReport {
+        metadata,
+        resources: &resources.resources,
+        resource_types,
+        totals: Totals {
+            cost_per_hour: resources.cost_per_hour()?,
+            cost_per_month: resources.cost_per_month()?,
+            cost_per_year: resources.cost_per_year()?,
+        },
+    })?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ndjson_has_a_document_per_line() {
+        assert_eq!(ndjson::<u8>(&[], "number"), "");
+        assert_eq!(ndjson(&[1, 2, 3], "number"), "1\n2\n3");
+        assert_eq!(
+            ndjson(
+                &[BTreeMap::from([("a", 1)]), BTreeMap::from([("b", 2)])],
+                "map"
+            ),
+            "{\"a\":1}\n{\"b\":2}"
+        );
     }
 }
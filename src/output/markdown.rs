@@ -1,9 +1,16 @@
 use comfy_table::{presets::ASCII_MARKDOWN, Cell, ContentArrangement, Table};
 use log::warn;
-use osc_cost::core::{digest::Drifts, Resource, Resources};
+use osc_cost::anomaly::Anomalies;
+use osc_cost::core::{
+    digest::{AccountDrifts, DailyDrifts, Drifts, MonthToDate, ResourceDrifts},
+    Resource, Resources,
+};
+use osc_cost::forecast::Forecast;
+use osc_cost::history::CostHistory;
 use std::error::Error;
 
 use super::get_currency;
+use super::human::projection;
 
 pub trait Markdown {
     fn markdown(&self) -> Result<String, Box<dyn Error>>;
@@ -111,6 +118,246 @@ impl Markdown for Drifts {
             ]);
         }
 
+        let mut out = format!("{table_resource}");
+        let entries = self
+            .drifts
+            .iter()
+            .flat_map(|drift| drift.entries.iter())
+            .collect::<Vec<_>>();
+        if !entries.is_empty() {
+            let mut table_entries = Table::new();
+            table_entries
+                .load_preset(ASCII_MARKDOWN)
+                .set_content_arrangement(ContentArrangement::Dynamic)
+                .set_width(100)
+                .set_header(vec!["Unmapped entry", "Value", "Digest"]);
+            for entry in entries {
+                table_entries.add_row(vec![
+                    entry.entry_id.clone(),
+                    format!("{:.2}", entry.value),
+                    format!("{:.2}", entry.price),
+                ]);
+            }
+            out.push_str(&format!("\n\n{table_entries}"));
+        }
+        Ok(out)
+    }
+}
+
+impl Markdown for CostHistory {
+    fn markdown(&self) -> Result<String, Box<dyn Error>> {
+        let mut table_resource = Table::new();
+        table_resource
+            .load_preset(ASCII_MARKDOWN)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_width(100)
+            .set_header(vec![
+                "Date",
+                "Resource Type",
+                "Count",
+                "Total price per hour",
+                "Total price per month",
+            ]);
+
+        for entry in &self.entries {
+            table_resource.add_row(vec![
+                entry.timestamp.clone(),
+                entry.resource_type.clone(),
+                format!("{}", entry.count),
+                format!("{:.2}", entry.price_per_hour),
+                format!("{:.2}", entry.price_per_month),
+            ]);
+        }
+
+        Ok(format!("{table_resource}"))
+    }
+}
+
+impl Markdown for Forecast {
+    fn markdown(&self) -> Result<String, Box<dyn Error>> {
+        let mut table_resource = Table::new();
+        table_resource
+            .load_preset(ASCII_MARKDOWN)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_width(120)
+            .set_header(vec![
+                "Resource Type".to_string(),
+                "Price per hour".to_string(),
+                "Trend per day".to_string(),
+                format!("Month {}", self.total.end_of_month.from_date),
+                format!("Quarter {}", self.total.end_of_quarter.from_date),
+                format!("Next {} days", self.horizon_days),
+            ]);
+
+        for forecast in self.forecasts.iter().chain([&self.total]) {
+            table_resource.add_row(vec![
+                forecast.resource_type.clone(),
+                format!("{:.4}", forecast.price_per_hour),
+                format!("{:+.4}", forecast.trend_per_day),
+                projection(&forecast.end_of_month),
+                projection(&forecast.end_of_quarter),
+                projection(&forecast.horizon),
+            ]);
+        }
+
+        Ok(format!("{table_resource}"))
+    }
+}
+
+impl Markdown for Anomalies {
+    fn markdown(&self) -> Result<String, Box<dyn Error>> {
+        if self.anomalies.is_empty() {
+            return Ok(format!("No anomaly against the last {} runs", self.runs));
+        }
+        let mut table_resource = Table::new();
+        table_resource
+            .load_preset(ASCII_MARKDOWN)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_width(100)
+            .set_header(vec![
+                "Dimension",
+                "Key",
+                "Kind",
+                "Baseline per hour",
+                "Price per hour",
+                "Change",
+                "Score",
+            ]);
+
+        for anomaly in &self.anomalies {
+            table_resource.add_row(vec![
+                anomaly.dimension.clone(),
+                anomaly.key.clone(),
+                anomaly.kind.clone(),
+                format!("{:.4}", anomaly.baseline_price_per_hour),
+                format!("{:.4}", anomaly.price_per_hour),
+                format!("{:+.0}%", anomaly.change_percent),
+                anomaly
+                    .score
+                    .map(|score| format!("{score:+.1}"))
+                    .unwrap_or_else(|| String::from("-")),
+            ]);
+        }
+
+        Ok(format!("{table_resource}"))
+    }
+}
+
+impl Markdown for MonthToDate {
+    fn markdown(&self) -> Result<String, Box<dyn Error>> {
+        let mut table_resource = Table::new();
+        table_resource
+            .load_preset(ASCII_MARKDOWN)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_width(100)
+            .set_header(vec![
+                "Resource Type".to_string(),
+                format!("Actual ({} - {})", self.from_date, self.to_date),
+                format!("Projected ({} - {})", self.to_date, self.end_date),
+                "Total".to_string(),
+            ]);
+
+        for category in &self.categories {
+            table_resource.add_row(vec![
+                category.category.clone(),
+                format!("{:.2}", category.actual),
+                format!("{:.2}", category.projected),
+                format!("{:.2}", category.total),
+            ]);
+        }
+        table_resource.add_row(vec![
+            "Total".to_string(),
+            format!("{:.2}", self.actual),
+            format!("{:.2}", self.projected),
+            format!("{:.2}", self.total),
+        ]);
+
+        Ok(format!("{table_resource}"))
+    }
+}
+
+impl Markdown for DailyDrifts {
+    fn markdown(&self) -> Result<String, Box<dyn Error>> {
+        let mut table_resource = Table::new();
+        table_resource
+            .load_preset(ASCII_MARKDOWN)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_width(100)
+            .set_header(vec!["Date", "Resource Type", "Osc-cost", "Digest", "Drift"]);
+
+        for daily in &self.drifts {
+            table_resource.add_row(vec![
+                daily.date.clone(),
+                daily.drift.category.clone(),
+                format!("{:.2}", daily.drift.osc_cost_price),
+                format!("{:.2}", daily.drift.digest_price),
+                format!("{}%", daily.drift.drift),
+            ]);
+        }
+
+        Ok(format!("{table_resource}"))
+    }
+}
+
+impl Markdown for ResourceDrifts {
+    fn markdown(&self) -> Result<String, Box<dyn Error>> {
+        let mut table_resource = Table::new();
+        table_resource
+            .load_preset(ASCII_MARKDOWN)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_width(100)
+            .set_header(vec![
+                "Resource Id",
+                "Resource Type",
+                "Status",
+                "Osc-cost",
+                "Digest",
+                "Drift",
+            ]);
+
+        for drift in &self.drifts {
+            table_resource.add_row(vec![
+                drift
+                    .resource_id
+                    .clone()
+                    .unwrap_or_else(|| String::from("-")),
+                drift.category.clone(),
+                drift.status.clone(),
+                format!("{:.2}", drift.osc_cost_price),
+                format!("{:.2}", drift.digest_price),
+                format!("{}%", drift.drift),
+            ]);
+        }
+
+        Ok(format!("{table_resource}"))
+    }
+}
+
+impl Markdown for AccountDrifts {
+    fn markdown(&self) -> Result<String, Box<dyn Error>> {
+        let mut table_resource = Table::new();
+        table_resource
+            .load_preset(ASCII_MARKDOWN)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_width(100)
+            .set_header(vec![
+                "Account",
+                "Resource Type",
+                "Osc-cost",
+                "Digest",
+                "Drift",
+            ]);
+
+        for account in &self.drifts {
+            table_resource.add_row(vec![
+                account.account_id.clone(),
+                account.drift.category.clone(),
+                format!("{:.2}", account.drift.osc_cost_price),
+                format!("{:.2}", account.drift.digest_price),
+                format!("{}%", account.drift.drift),
+            ]);
+        }
+
         Ok(format!("{table_resource}"))
     }
 }
@@ -1,8 +1,272 @@
-use osc_cost::core::Resources;
+use icu_locale_core::locale;
+use osc_cost::core::{digest::Drifts, price_per_year, Resources, HOURS_PER_MONTH};
+use spreadsheet_ods::{
+    defaultstyles::DefaultFormat,
+    format::create_currency_suffix,
+    formula::{fcellref, fcellref_table, frangeref, frangeref_table},
+    CellStyle, CellStyleRef, Sheet, WorkBook,
+};
+
+use super::get_currency;
 
 mod error;
 mod ser;
 
-pub fn ods(resources: &Resources) -> error::Result<Vec<u8>> {
-    ser::to_bytes(&resources.resources)
+struct Styles {
+    header: CellStyleRef,
+    currency: CellStyleRef,
+    total: CellStyleRef,
+}
+
+// A Summary sheet whose totals are formulas on the sheets of each resource type, then
+// those sheets, then the drift when computed.
+pub fn ods(resources: &Resources, drifts: Option<&Drifts>) -> error::Result<Vec<u8>> {
+    let region = resources
+        .resources
+        .first()
+        .and_then(|r| r.region())
+        .unwrap_or_default();
+
+    let mut wb = WorkBook::new(locale!("en-US"));
+    let mut st_header = CellStyle::new("header", &DefaultFormat::default());
+    st_header.set_font_bold();
+    let currency_format = wb.add_currency_format(create_currency_suffix(
+        "currency",
+        locale!("en-US"),
+        get_currency(region),
+    ));
+    let mut st_total = CellStyle::new("total", &currency_format);
+    st_total.set_font_bold();
+    let styles = Styles {
+        header: wb.add_cellstyle(st_header),
+        currency: wb.add_cellstyle(CellStyle::new("price", &currency_format)),
+        total: wb.add_cellstyle(st_total),
+    };
+
+    let sheets = ser::to_sheets(
+        &resources.resources,
+        styles.header.clone(),
+        styles.currency.clone(),
+    )?;
+    wb.push_sheet(summary(&sheets, &styles));
+    for sheet in sheets {
+        wb.push_sheet(sheet);
+    }
+    if let Some(drifts) = drifts {
+        wb.push_sheet(drift(drifts, &styles));
+    }
+
+    let data: Vec<u8> = Vec::new();
+    spreadsheet_ods::write_ods_buf(&mut wb, data).map_err(|e| error::Error::Message(e.to_string()))
+}
+
+fn summary(sheets: &[Sheet], styles: &Styles) -> Sheet {
+    let mut summary = Sheet::new("Summary");
+    for (col, title) in [
+        "Resource Type",
+        "Count",
+        "Total price per hour",
+        "Total price per month",
+        "Total price per year",
+    ]
+    .iter()
+    .enumerate()
+    {
+        summary.set_styled_value(0, col as u32, *title, &styles.header);
+    }
+
+    let mut row = 1;
+    for sheet in sheets {
+        let name = sheet.name().clone();
+        let (rows, cols) = sheet.used_grid_size();
+        let column =
+            |title: &str| (0..cols).find(|col| sheet.value(0, *col).as_str_or("") == title);
+        let (Some(price_per_hour), Some(price_per_month)) =
+            (column("price_per_hour"), column("price_per_month"))
+        else {
+            continue;
+        };
+
+        // The aggregates already are totals, reference each of them
+        if let (Some(count), Some(resource_type)) =
+            (column("count"), column("aggregated_resource_type"))
+        {
+            for aggregate in 1..rows {
+                summary.set_value(
+                    row,
+                    0,
+                    sheet
+                        .value(aggregate, resource_type)
+                        .as_str_or("")
+                        .to_string(),
+                );
+                for (col, source) in [(1, count), (2, price_per_hour), (3, price_per_month)] {
+                    summary.set_value(row, col, sheet.value(aggregate, source).as_f64_or(0.0));
+                    summary.set_formula(
+                        row,
+                        col,
+                        format!("of:={}", fcellref_table(name.as_str(), aggregate, source)),
+                    );
+                }
+                year_price(&mut summary, row, styles);
+                row += 1;
+            }
+            continue;
+        }
+
+        let last = rows.saturating_sub(1).max(1);
+        let sum = |col: u32| {
+            (1..rows)
+                .map(|row| sheet.value(row, col).as_f64_or(0.0))
+                .sum::<f64>()
+        };
+        summary.set_value(row, 0, name.clone());
+        summary.set_value(row, 1, rows.saturating_sub(1));
+        summary.set_formula(
+            row,
+            1,
+            format!(
+                "of:=COUNTA({})",
+                frangeref_table(name.as_str(), 1, 0, last, 0)
+            ),
+        );
+        for (col, source) in [(2, price_per_hour), (3, price_per_month)] {
+            summary.set_value(row, col, sum(source));
+            summary.set_formula(
+                row,
+                col,
+                format!(
+                    "of:=SUM({})",
+                    frangeref_table(name.as_str(), 1, source, last, source)
+                ),
+            );
+        }
+        year_price(&mut summary, row, styles);
+        row += 1;
+    }
+
+    summary.set_styled_value(row, 0, "Total", &styles.header);
+    for col in 1..5 {
+        let total = (1..row)
+            .map(|r| summary.value(r, col).as_f64_or(0.0))
+            .sum::<f64>();
+        summary.set_value(row, col, total);
+        if row > 1 {
+            summary.set_formula(
+                row,
+                col,
+                format!("of:=SUM({})", frangeref(1, col, row - 1, col)),
+            );
+        }
+        match col {
+            1 => summary.set_cellstyle(row, col, &styles.header),
+            _ => summary.set_cellstyle(row, col, &styles.total),
+        }
+    }
+    summary.set_header_rows(0, 0);
+    summary
+}
+
+// From the price per hour like cost_per_year, the month of a dedicated VM misses its factor
+fn year_price(summary: &mut Sheet, row: u32, styles: &Styles) {
+    let price_per_hour = summary.value(row, 2).as_f64_or(0.0) as f32;
+    summary.set_value(row, 4, price_per_year(price_per_hour));
+    summary.set_formula(
+        row,
+        4,
+        format!("of:={}*{HOURS_PER_MONTH}*12", fcellref(row, 2)),
+    );
+    for col in 2..5 {
+        summary.set_cellstyle(row, col, &styles.currency);
+    }
+}
+
+fn drift(drifts: &Drifts, styles: &Styles) -> Sheet {
+    let mut sheet = Sheet::new("Drift");
+    for (col, title) in [
+        "Resource Type",
+        "Osc-cost",
+        "Digest",
+        "Difference",
+        "Drift (%)",
+    ]
+    .iter()
+    .enumerate()
+    {
+        sheet.set_styled_value(0, col as u32, *title, &styles.header);
+    }
+
+    let mut row = 1;
+    for drift in &drifts.drifts {
+        sheet.set_value(row, 0, drift.category.clone());
+        sheet.set_styled_value(row, 1, drift.osc_cost_price, &styles.currency);
+        sheet.set_styled_value(row, 2, drift.digest_price, &styles.currency);
+        sheet.set_styled_value(
+            row,
+            3,
+            drift.osc_cost_price - drift.digest_price,
+            &styles.currency,
+        );
+        sheet.set_formula(
+            row,
+            3,
+            format!("of:={}-{}", fcellref(row, 1), fcellref(row, 2)),
+        );
+        sheet.set_value(row, 4, drift.drift);
+        row += 1;
+    }
+
+    sheet.set_styled_value(row, 0, "Total", &styles.header);
+    for col in 1..4 {
+        let total = (1..row)
+            .map(|r| sheet.value(r, col).as_f64_or(0.0))
+            .sum::<f64>();
+        sheet.set_styled_value(row, col, total, &styles.total);
+        if row > 1 {
+            sheet.set_formula(
+                row,
+                col,
+                format!("of:=SUM({})", frangeref(1, col, row - 1, col)),
+            );
+        }
+    }
+    sheet.set_header_rows(0, 0);
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use osc_cost::core::{vms::Vm, Resource};
+
+    use super::*;
+
+    #[test]
+    fn summary_year_follows_the_hourly_price() {
+        let mut resources = Resources {
+            resources: vec![Resource::Vm(Vm {
+                vm_vcpu: 2,
+                vm_ram_gb: 4,
+                price_vcpu_per_hour: 0.1,
+                price_ram_gb_per_hour: 0.05,
+                // Dedicated VM
+                factor_vm_additional_cost: 1.1,
+                ..Default::default()
+            })],
+        };
+        resources.compute().unwrap();
+
+        let wb = spreadsheet_ods::read_ods_buf(&ods(&resources, None).unwrap()).unwrap();
+        let summary = wb.sheet(0);
+        assert_eq!(summary.name(), "Summary");
+        assert_eq!(summary.value(1, 0).as_str_or(""), "Vm");
+        assert_eq!(
+            summary.formula(1, 4).map(String::as_str),
+            Some("of:=[.C2]*730*12")
+        );
+        // Total row
+        let year = summary.value(2, 4).as_f64_or(0.0) as f32;
+        assert!((year - resources.cost_per_year().unwrap()).abs() < 1e-2);
+        let month = summary.value(2, 3).as_f64_or(0.0) as f32;
+        assert!((year - month * 12.0).abs() > 1.0);
+    }
 }
@@ -1,12 +1,10 @@
 use std::collections::HashMap;
 
-use icu_locale_core::locale;
-
 use serde::{
     ser::{self, Impossible},
     Serialize,
 };
-use spreadsheet_ods::{defaultstyles::DefaultFormat, CellStyle, CellStyleRef, Sheet, WorkBook};
+use spreadsheet_ods::{CellStyleRef, Sheet, Value};
 
 use super::error::{Error, Result};
 
@@ -15,6 +13,7 @@ pub struct Serializer {
     sheets: HashMap<String, Sheet>,
     sheet_state: Option<SheetState>,
     cell_style: CellStyleRef,
+    currency_style: CellStyleRef,
 }
 
 #[derive(Debug)]
@@ -26,29 +25,27 @@ pub struct SheetState {
     name: String,
 }
 
-pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>>
+// One sheet per struct name, sorted by name. Price columns use the currency style.
+pub fn to_sheets<T>(
+    value: &T,
+    header_style: CellStyleRef,
+    currency_style: CellStyleRef,
+) -> Result<Vec<Sheet>>
 where
     T: Serialize,
 {
-    let mut wb = WorkBook::new(locale!("en-US"));
-
-    let mut st_header = CellStyle::new("header", &DefaultFormat::default());
-    st_header.set_font_bold();
-    let cell_style_ref = wb.add_cellstyle(st_header);
-
     let mut serializer = Serializer {
         sheets: HashMap::new(),
         sheet_state: None,
-        cell_style: cell_style_ref,
+        cell_style: header_style,
+        currency_style,
     };
 
     value.serialize(&mut serializer)?;
 
-    for sheet in serializer.sheets {
-        wb.push_sheet(sheet.1);
-    }
-    let data: Vec<u8> = Vec::new();
-    spreadsheet_ods::write_ods_buf(&mut wb, data).map_err(|e| Error::Message(e.to_string()))
+    let mut sheets = serializer.sheets.into_values().collect::<Vec<Sheet>>();
+    sheets.sort_by(|a, b| a.name().cmp(b.name()));
+    Ok(sheets)
 }
 
 impl Serializer {
@@ -399,11 +396,25 @@ impl ser::SerializeStruct for &mut Serializer {
             );
         }
 
-        let res = value.serialize(&mut **self);
+        // Maps (resource tags) are written as JSON objects
+        let res = match serde_json::to_value(value) {
+            Ok(object @ serde_json::Value::Object(_)) => self.serialize_value(object.to_string()),
+            _ => value.serialize(&mut **self),
+        };
 
         let Some(sheet_state) = &mut self.sheet_state else {
             return Err(Error::ExpectedStartStruct);
         };
+        if key.starts_with("price_")
+            && matches!(
+                sheet_state.sheet.value(sheet_state.row, sheet_state.col),
+                Value::Number(_)
+            )
+        {
+            sheet_state
+                .sheet
+                .set_cellstyle(sheet_state.row, sheet_state.col, &self.currency_style);
+        }
         sheet_state.col += 1;
 
         res
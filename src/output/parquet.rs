@@ -0,0 +1,222 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Date32Array, Decimal128Array, Float64Array, Int64Array, RecordBatch,
+    StringArray, TimestampMicrosecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::DateTime;
+use osc_cost::core::Resources;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde_json::Value;
+
+use super::csv::{aggregated, columns, fields, text};
+
+const PRICE_PRECISION: u8 = 18;
+const PRICE_SCALE: i8 = 6;
+// Derived from read_date_rfc3339, to partition the files by day
+const DATE_COLUMN: &str = "read_date";
+
+// Same stable columns as the csv output, typed from the default value of each field: prices
+// are decimals, the read date is a timestamp and every column is nullable as most of them
+// only exist for some resource types.
+pub fn parquet(resources: &Resources) -> Result<Vec<u8>, Box<dyn Error>> {
+    let rows = resources
+        .resources
+        .iter()
+        .map(fields)
+        .collect::<Result<Vec<_>, _>>()?;
+    let value = |row: &Vec<(String, Value)>, column: &str| {
+        row.iter()
+            .find(|(key, _)| key == column)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    };
+
+    let mut schema = Vec::<Field>::new();
+    let mut arrays = Vec::<ArrayRef>::new();
+    for (column, default) in columns(aggregated(resources))? {
+        let values = rows
+            .iter()
+            .map(|row| value(row, &column))
+            .collect::<Vec<Value>>();
+        let (data_type, array) = array(&column, &default, &values)?;
+        schema.push(Field::new(column.as_str(), data_type, true));
+        arrays.push(array);
+
+        if column == "read_date_rfc3339" {
+            let dates = values
+                .iter()
+                .map(|value| {
+                    timestamp(value).map(|micros| micros.div_euclid(86_400_000_000) as i32)
+                })
+                .collect::<Vec<Option<i32>>>();
+            schema.push(Field::new(DATE_COLUMN, DataType::Date32, true));
+            arrays.push(Arc::new(Date32Array::from(dates)));
+        }
+    }
+
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(schema)), arrays)?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(Vec::new(), batch.schema(), Some(properties))?;
+    writer.write(&batch)?;
+    Ok(writer.into_inner()?)
+}
+
+fn array(
+    column: &str,
+    default: &Value,
+    values: &[Value],
+) -> Result<(DataType, ArrayRef), Box<dyn Error>> {
+    if column == "read_date_rfc3339" {
+        let timestamps = values.iter().map(timestamp).collect::<Vec<Option<i64>>>();
+        let array = TimestampMicrosecondArray::from(timestamps).with_timezone("UTC");
+        return Ok((
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            Arc::new(array),
+        ));
+    }
+    if column.starts_with("price_") {
+        let prices = values
+            .iter()
+            .map(|value| match value {
+                Value::Number(_) => text(value)
+                    .parse::<f64>()
+                    .ok()
+                    .map(|price| (price * 10f64.powi(PRICE_SCALE.into())).round() as i128),
+                _ => None,
+            })
+            .collect::<Vec<Option<i128>>>();
+        let array =
+            Decimal128Array::from(prices).with_precision_and_scale(PRICE_PRECISION, PRICE_SCALE)?;
+        return Ok((
+            DataType::Decimal128(PRICE_PRECISION, PRICE_SCALE),
+            Arc::new(array),
+        ));
+    }
+
+    Ok(match default {
+        Value::Bool(_) => (
+            DataType::Boolean,
+            Arc::new(BooleanArray::from(
+                values.iter().map(Value::as_bool).collect::<Vec<_>>(),
+            )),
+        ),
+        Value::Number(number) if number.is_f64() => (
+            DataType::Float64,
+            Arc::new(Float64Array::from(
+                values
+                    .iter()
+                    .map(|value| value.as_f64().and_then(|_| text(value).parse().ok()))
+                    .collect::<Vec<Option<f64>>>(),
+            )),
+        ),
+        Value::Number(_) => (
+            DataType::Int64,
+            Arc::new(Int64Array::from(
+                values.iter().map(Value::as_i64).collect::<Vec<_>>(),
+            )),
+        ),
+        _ => (
+            DataType::Utf8,
+            Arc::new(StringArray::from(
+                values
+                    .iter()
+                    .map(|value| match value {
+                        Value::Null => None,
+                        value => Some(text(value)),
+                    })
+                    .collect::<Vec<Option<String>>>(),
+            )),
+        ),
+    })
+}
+
+fn timestamp(value: &Value) -> Option<i64> {
+    value
+        .as_str()
+        .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+        .map(|date| date.timestamp_micros())
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::Array;
+    use osc_cost::core::{vms::Vm, volumes::Volume, Resource};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+
+    #[test]
+    fn written_file_reads_back() {
+        let resources = Resources {
+            resources: vec![
+                Resource::Vm(Vm {
+                    vm_type: Some(String::from("tinav5.c2r4p2")),
+                    read_date_rfc3339: Some(String::from("2024-04-01T10:00:00Z")),
+                    price_per_hour: Some(0.44),
+                    ..Default::default()
+                }),
+                Resource::Volume(Volume {
+                    volume_iops: Some(100),
+                    read_date_rfc3339: Some(String::from("2024-04-01T10:00:00Z")),
+                    price_per_hour: Some(0.5),
+                    ..Default::default()
+                }),
+            ],
+        };
+        let file = bytes::Bytes::from(parquet(&resources).unwrap());
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+
+        // Same columns as the csv output, plus the read date
+        let schema = reader.schema().clone();
+        let expected = columns(false)
+            .unwrap()
+            .into_iter()
+            .map(|(column, _)| column)
+            .collect::<Vec<String>>();
+        let names = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .filter(|name| *name != DATE_COLUMN)
+            .collect::<Vec<&str>>();
+        assert_eq!(names, expected);
+        let data_type = |name: &str| schema.field_with_name(name).unwrap().data_type().clone();
+        assert_eq!(
+            data_type("read_date_rfc3339"),
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+        );
+        assert_eq!(data_type(DATE_COLUMN), DataType::Date32);
+        assert_eq!(
+            data_type("price_per_hour"),
+            DataType::Decimal128(PRICE_PRECISION, PRICE_SCALE)
+        );
+        assert_eq!(data_type("vm_type"), DataType::Utf8);
+        assert_eq!(data_type("volume_iops"), DataType::Int64);
+
+        let batches = reader
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 2);
+        let batch = &batches[0];
+        let prices = batch
+            .column_by_name("price_per_hour")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Decimal128Array>()
+            .unwrap();
+        assert_eq!(prices.value(0), 440_000);
+        assert_eq!(prices.value(1), 500_000);
+        let vm_types = batch.column_by_name("vm_type").unwrap();
+        assert!(vm_types.is_valid(0));
+        assert!(vm_types.is_null(1));
+    }
+}
@@ -1,11 +1,18 @@
-use osc_cost::core::Resources;
+use osc_cost::anomaly::Anomalies;
+use osc_cost::core::{digest::Drifts, Resources};
 
-use self::ser::{to_prom, CustomLabelKey};
+use prometheus::{proto::MetricFamily, GaugeVec, Opts, Registry};
+
+use self::error::Error;
+pub use self::ser::encode;
+use self::ser::{to_metric_families, CustomLabelKey};
 
 mod error;
+pub mod otlp;
+pub mod push;
 mod ser;
 
-pub fn prometheus(resources: &Resources) -> error::Result<String> {
+pub fn metric_families(resources: &Resources) -> error::Result<Vec<MetricFamily>> {
     let keep_label = vec![
         "account_id".to_string(),
         "osc_cost_version".to_string(),
@@ -36,7 +43,7 @@ pub fn prometheus(resources: &Resources) -> error::Result<String> {
         key: secondary_label_key,
     };
 
-    to_prom(
+    to_metric_families(
         &resources.resources,
         keep_label,
         primary,
@@ -44,3 +51,95 @@ pub fn prometheus(resources: &Resources) -> error::Result<String> {
         label_type,
     )
 }
+
+// One gauge per drift category, for the digest and the osc-cost estimate over the same period.
+pub fn drift_metric_families(drifts: &Drifts) -> error::Result<Vec<MetricFamily>> {
+    let registry = Registry::new();
+    let drift_percent = gauge_vec(
+        &registry,
+        "osc_cost_drift_percent",
+        "drift between the estimated and the billed price in percent",
+        &["category"],
+    )?;
+    let digest_price = gauge_vec(
+        &registry,
+        "osc_cost_digest_price",
+        "price billed in the consumption digest over the drift period",
+        &["category"],
+    )?;
+    let estimated_price = gauge_vec(
+        &registry,
+        "osc_cost_estimated_price",
+        "price estimated by osc-cost over the drift period",
+        &["category"],
+    )?;
+
+    for drift in &drifts.drifts {
+        let labels = [drift.category.as_str()];
+        drift_percent
+            .with_label_values(&labels)
+            .set(drift.drift as f64);
+        digest_price
+            .with_label_values(&labels)
+            .set(drift.digest_price as f64);
+        estimated_price
+            .with_label_values(&labels)
+            .set(drift.osc_cost_price as f64);
+    }
+    Ok(registry.gather())
+}
+
+fn gauge_vec(
+    registry: &Registry,
+    name: &str,
+    help: &str,
+    labels: &[&str],
+) -> error::Result<GaugeVec> {
+    let gauge =
+        GaugeVec::new(Opts::new(name, help), labels).map_err(|e| Error::Message(e.to_string()))?;
+    registry
+        .register(Box::new(gauge.clone()))
+        .map_err(|e| Error::Message(e.to_string()))?;
+    Ok(gauge)
+}
+
+// Anomalies found against the history baseline, with their count so alerts can fire on it.
+pub fn anomaly_metric_families(anomalies: &Anomalies) -> error::Result<Vec<MetricFamily>> {
+    let registry = Registry::new();
+    let count = gauge_vec(
+        &registry,
+        "osc_cost_anomalies",
+        "number of cost anomalies against the history baseline",
+        &[],
+    )?;
+    let score = gauge_vec(
+        &registry,
+        "osc_cost_anomaly_score",
+        "deviation of the hourly price from the baseline in standard deviations",
+        &["dimension", "key", "kind"],
+    )?;
+    let change_percent = gauge_vec(
+        &registry,
+        "osc_cost_anomaly_change_percent",
+        "change of the hourly price against the baseline in percent",
+        &["dimension", "key", "kind"],
+    )?;
+
+    count
+        .with_label_values::<&str>(&[])
+        .set(anomalies.anomalies.len() as f64);
+    for anomaly in &anomalies.anomalies {
+        let labels = [
+            anomaly.dimension.as_str(),
+            anomaly.key.as_str(),
+            anomaly.kind.as_str(),
+        ];
+        if let Some(value) = anomaly.score {
+            score.with_label_values(&labels).set(value);
+        }
+        change_percent
+            .with_label_values(&labels)
+            .set(anomaly.change_percent);
+    }
+    Ok(registry.gather())
+}
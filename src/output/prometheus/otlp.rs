@@ -0,0 +1,282 @@
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use prometheus::proto::MetricFamily;
+use reqwest::blocking::Client;
+
+use super::error::{Error, Result};
+use super::push::{encode_bytes, encode_key, send, PushConfig, TIMEOUT};
+
+// Labels moved to the resource attributes, with their semantic convention names
+const RESOURCE_LABELS: [(&str, &str); 2] = [
+    ("account_id", "cloud.account.id"),
+    ("region", "cloud.region"),
+];
+
+// Send the metrics as OTLP gauges to a collector, over HTTP with protobuf encoding. The
+// datapoints are grouped by account and region, the other labels are kept as attributes.
+pub fn otlp(url: &str, config: &PushConfig, metric_families: &[MetricFamily]) -> Result<()> {
+    let endpoint = match url.trim_end_matches('/') {
+        url if url.ends_with("/v1/metrics") => url.to_string(),
+        url => format!("{url}/v1/metrics"),
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| Error::Message(e.to_string()))?
+        .as_nanos() as u64;
+
+    let client = Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(|e| Error::Message(e.to_string()))?;
+    let request = client
+        .post(&endpoint)
+        .header("Content-Type", "application/x-protobuf")
+        .body(export_request(config, metric_families, timestamp));
+    send(config.apply_headers(request)?, &endpoint)
+}
+
+// Protobuf encoding of opentelemetry.proto.collector.metrics.v1.ExportMetricsServiceRequest:
+// ExportMetricsServiceRequest { repeated ResourceMetrics resource_metrics = 1; }
+// ResourceMetrics { Resource resource = 1; repeated ScopeMetrics scope_metrics = 2; }
+// Resource { repeated KeyValue attributes = 1; }
+// ScopeMetrics { InstrumentationScope scope = 1; repeated Metric metrics = 2; }
+// InstrumentationScope { string name = 1; string version = 2; }
+// Metric { string name = 1; string description = 2; Gauge gauge = 5; }
+// Gauge { repeated NumberDataPoint data_points = 1; }
+// NumberDataPoint { fixed64 time_unix_nano = 3; double as_double = 4; repeated KeyValue attributes = 7; }
+// KeyValue { string key = 1; AnyValue value = 2; }
+// AnyValue { string string_value = 1; }
+fn export_request(
+    config: &PushConfig,
+    metric_families: &[MetricFamily],
+    timestamp: u64,
+) -> Vec<u8> {
+    // Data points per resource (account and region), then per metric family
+    let mut resources = BTreeMap::<Vec<(&str, &str)>, BTreeMap<usize, Vec<u8>>>::new();
+    for (index, family) in metric_families.iter().enumerate() {
+        for metric in family.get_metric() {
+            let mut resource = Vec::new();
+            let mut data_point = Vec::new();
+            encode_key(3, 1, &mut data_point);
+            data_point.extend_from_slice(&timestamp.to_le_bytes());
+            encode_key(4, 1, &mut data_point);
+            data_point.extend_from_slice(&metric.get_gauge().value().to_le_bytes());
+            for label in metric.get_label() {
+                match RESOURCE_LABELS
+                    .iter()
+                    .find(|(name, _)| *name == label.name())
+                {
+                    Some((_, attribute)) => resource.push((*attribute, label.value())),
+                    None => {
+                        encode_bytes(7, &key_value(label.name(), label.value()), &mut data_point)
+                    }
+                }
+            }
+            resource.sort();
+
+            let points = resources
+                .entry(resource)
+                .or_default()
+                .entry(index)
+                .or_default();
+            encode_bytes(1, &data_point, points);
+        }
+    }
+
+    let mut request = Vec::new();
+    for (attributes, metrics) in resources {
+        let mut resource = Vec::new();
+        encode_bytes(1, &key_value("service.name", &config.job), &mut resource);
+        if let Some(instance) = &config.instance {
+            encode_bytes(
+                1,
+                &key_value("service.instance.id", instance),
+                &mut resource,
+            );
+        }
+        encode_bytes(1, &key_value("cloud.provider", "outscale"), &mut resource);
+        for (key, value) in attributes {
+            encode_bytes(1, &key_value(key, value), &mut resource);
+        }
+
+        let mut scope_metrics = Vec::new();
+        let mut scope = Vec::new();
+        encode_bytes(1, env!("CARGO_PKG_NAME").as_bytes(), &mut scope);
+        encode_bytes(2, env!("CARGO_PKG_VERSION").as_bytes(), &mut scope);
+        encode_bytes(1, &scope, &mut scope_metrics);
+        for (index, points) in metrics {
+            let Some(family) = metric_families.get(index) else {
+                continue;
+            };
+            let mut metric = Vec::new();
+            encode_bytes(1, family.name().as_bytes(), &mut metric);
+            encode_bytes(2, family.help().as_bytes(), &mut metric);
+            encode_bytes(5, &points, &mut metric);
+            encode_bytes(2, &metric, &mut scope_metrics);
+        }
+
+        let mut resource_metrics = Vec::new();
+        encode_bytes(1, &resource, &mut resource_metrics);
+        encode_bytes(2, &scope_metrics, &mut resource_metrics);
+        encode_bytes(1, &resource_metrics, &mut request);
+    }
+    request
+}
+
+fn key_value(key: &str, value: &str) -> Vec<u8> {
+    let mut any_value = Vec::new();
+    encode_bytes(1, value.as_bytes(), &mut any_value);
+    let mut key_value = Vec::new();
+    encode_bytes(1, key.as_bytes(), &mut key_value);
+    encode_bytes(2, &any_value, &mut key_value);
+    key_value
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::{GaugeVec, Opts, Registry};
+
+    use super::*;
+
+    // Fields of a protobuf message, by field number: fixed64 values and length-delimited bytes
+    enum Value<'a> {
+        Fixed64(u64),
+        Bytes(&'a [u8]),
+    }
+
+    fn decode_varint(buf: &mut &[u8]) -> u64 {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (byte, rest) = buf.split_first().unwrap();
+            *buf = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        value
+    }
+
+    fn decode(mut buf: &[u8]) -> Vec<(u64, Value<'_>)> {
+        let mut fields = Vec::new();
+        while !buf.is_empty() {
+            let key = decode_varint(&mut buf);
+            let value = match key & 7 {
+                1 => {
+                    let (value, rest) = buf.split_at(8);
+                    buf = rest;
+                    Value::Fixed64(u64::from_le_bytes(value.try_into().unwrap()))
+                }
+                2 => {
+                    let len = decode_varint(&mut buf) as usize;
+                    let (value, rest) = buf.split_at(len);
+                    buf = rest;
+                    Value::Bytes(value)
+                }
+                wire_type => panic!("unexpected wire type {wire_type}"),
+            };
+            fields.push((key >> 3, value));
+        }
+        fields
+    }
+
+    fn messages(buf: &[u8], field: u64) -> Vec<&[u8]> {
+        decode(buf)
+            .into_iter()
+            .filter_map(|(number, value)| match value {
+                Value::Bytes(bytes) if number == field => Some(bytes),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn fixed64(buf: &[u8], field: u64) -> u64 {
+        decode(buf)
+            .into_iter()
+            .find_map(|(number, value)| match value {
+                Value::Fixed64(value) if number == field => Some(value),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn string(buf: &[u8], field: u64) -> &str {
+        std::str::from_utf8(messages(buf, field)[0]).unwrap()
+    }
+
+    fn attributes(buf: &[u8], field: u64) -> BTreeMap<&str, &str> {
+        messages(buf, field)
+            .into_iter()
+            .map(|key_value| (string(key_value, 1), string(messages(key_value, 2)[0], 1)))
+            .collect()
+    }
+
+    #[test]
+    fn export_request_round_trip() {
+        let registry = Registry::new();
+        let gauge = GaugeVec::new(
+            Opts::new("osc_cost_price_per_hour", "Price per hour"),
+            &["account_id", "region", "resource_id"],
+        )
+        .unwrap();
+        registry.register(Box::new(gauge.clone())).unwrap();
+        gauge
+            .with_label_values(&["1", "eu-west-2", "vol-1"])
+            .set(0.5);
+        gauge
+            .with_label_values(&["1", "eu-west-2", "vol-2"])
+            .set(1.5);
+        gauge
+            .with_label_values(&["2", "us-east-2", "vol-3"])
+            .set(2.0);
+        let config = PushConfig {
+            job: "osc-cost".to_string(),
+            instance: Some("host".to_string()),
+            headers: Vec::new(),
+        };
+
+        let request = export_request(&config, &registry.gather(), 42);
+        let resource_metrics = messages(&request, 1);
+        assert_eq!(resource_metrics.len(), 2);
+
+        let resource = messages(resource_metrics[0], 1)[0];
+        assert_eq!(
+            attributes(resource, 1),
+            BTreeMap::from([
+                ("cloud.account.id", "1"),
+                ("cloud.provider", "outscale"),
+                ("cloud.region", "eu-west-2"),
+                ("service.instance.id", "host"),
+                ("service.name", "osc-cost"),
+            ])
+        );
+
+        let scope_metrics = messages(resource_metrics[0], 2)[0];
+        let scope = messages(scope_metrics, 1)[0];
+        assert_eq!(string(scope, 1), env!("CARGO_PKG_NAME"));
+        let metrics = messages(scope_metrics, 2);
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(string(metrics[0], 1), "osc_cost_price_per_hour");
+        assert_eq!(string(metrics[0], 2), "Price per hour");
+
+        let gauge = messages(metrics[0], 5)[0];
+        let data_points = messages(gauge, 1)
+            .into_iter()
+            .map(|data_point| {
+                assert_eq!(fixed64(data_point, 3), 42);
+                (
+                    attributes(data_point, 7),
+                    f64::from_bits(fixed64(data_point, 4)),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            data_points,
+            vec![
+                (BTreeMap::from([("resource_id", "vol-1")]), 0.5),
+                (BTreeMap::from([("resource_id", "vol-2")]), 1.5),
+            ]
+        );
+    }
+}
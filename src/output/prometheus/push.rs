@@ -0,0 +1,193 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use log::info;
+use prometheus::proto::MetricFamily;
+use reqwest::blocking::{Client, RequestBuilder};
+
+use super::error::{Error, Result};
+use super::ser::encode;
+
+pub(super) const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct PushConfig {
+    pub job: String,
+    pub instance: Option<String>,
+    // Extra HTTP headers formatted as "Name: value" (e.g. "Authorization: Bearer ...")
+    pub headers: Vec<String>,
+}
+
+impl PushConfig {
+    pub(super) fn apply_headers(&self, mut request: RequestBuilder) -> Result<RequestBuilder> {
+        for header in &self.headers {
+            let Some((name, value)) = header.split_once(':') else {
+                return Err(Error::Message(format!(
+                    "invalid header {header}, expected \"Name: value\""
+                )));
+            };
+            request = request.header(name.trim(), value.trim());
+        }
+        Ok(request)
+    }
+}
+
+// Replace the metrics of the job/instance group on a Pushgateway.
+pub fn push_gateway(
+    url: &str,
+    config: &PushConfig,
+    metric_families: &[MetricFamily],
+) -> Result<()> {
+    let mut endpoint = format!(
+        "{}/metrics/{}",
+        url.trim_end_matches('/'),
+        grouping_key("job", &config.job)
+    );
+    if let Some(instance) = &config.instance {
+        endpoint.push('/');
+        endpoint.push_str(&grouping_key("instance", instance));
+    }
+
+    let client = Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(|e| Error::Message(e.to_string()))?;
+    let request = client
+        .put(&endpoint)
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .body(encode(metric_families)?);
+    send(config.apply_headers(request)?, &endpoint)
+}
+
+// Send the metrics as a Prometheus remote-write 1.0 request.
+pub fn remote_write(
+    url: &str,
+    config: &PushConfig,
+    metric_families: &[MetricFamily],
+) -> Result<()> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| Error::Message(e.to_string()))?
+        .as_millis() as i64;
+    let body = snap::raw::Encoder::new()
+        .compress_vec(&write_request(config, metric_families, timestamp))
+        .map_err(|e| Error::Message(e.to_string()))?;
+
+    let client = Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(|e| Error::Message(e.to_string()))?;
+    let request = client
+        .post(url)
+        .header("Content-Encoding", "snappy")
+        .header("Content-Type", "application/x-protobuf")
+        .header("X-Prometheus-Remote-Write-Version", "0.1.0")
+        .body(body);
+    send(config.apply_headers(request)?, url)
+}
+
+pub(super) fn send(request: RequestBuilder, endpoint: &str) -> Result<()> {
+    let response = request
+        .send()
+        .map_err(|e| Error::Message(format!("cannot push metrics to {endpoint}: {e}")))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().unwrap_or_default();
+        return Err(Error::Message(format!(
+            "cannot push metrics to {endpoint}: {status} {body}"
+        )));
+    }
+    info!("pushed metrics to {endpoint}");
+    Ok(())
+}
+
+// Pushgateway grouping key, base64 encoded unless the value only has unreserved URL
+// characters (and is not a dot segment)
+fn grouping_key(name: &str, value: &str) -> String {
+    let unreserved = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~'));
+    match unreserved && !matches!(value, "" | "." | "..") {
+        true => format!("{name}/{value}"),
+        // The Pushgateway expects "=" for an empty value
+        false if value.is_empty() => format!("{name}@base64/="),
+        false => format!("{name}@base64/{}", URL_SAFE_NO_PAD.encode(value)),
+    }
+}
+
+// Protobuf encoding of prometheus.WriteRequest:
+// WriteRequest { repeated TimeSeries timeseries = 1; }
+// TimeSeries { repeated Label labels = 1; repeated Sample samples = 2; }
+// Label { string name = 1; string value = 2; }
+// Sample { double value = 1; int64 timestamp = 2; }
+fn write_request(config: &PushConfig, metric_families: &[MetricFamily], timestamp: i64) -> Vec<u8> {
+    let mut request = Vec::new();
+    for family in metric_families {
+        for metric in family.get_metric() {
+            let mut labels = vec![("__name__", family.name()), ("job", config.job.as_str())];
+            if let Some(instance) = &config.instance {
+                labels.push(("instance", instance.as_str()));
+            }
+            for label in metric.get_label() {
+                labels.push((label.name(), label.value()));
+            }
+            // Remote-write receivers expect labels sorted by name
+            labels.sort_by(|a, b| a.0.cmp(b.0));
+
+            let mut series = Vec::new();
+            for (name, value) in labels {
+                let mut label = Vec::new();
+                encode_bytes(1, name.as_bytes(), &mut label);
+                encode_bytes(2, value.as_bytes(), &mut label);
+                encode_bytes(1, &label, &mut series);
+            }
+            let mut sample = Vec::new();
+            encode_key(1, 1, &mut sample);
+            sample.extend_from_slice(&metric.get_gauge().value().to_le_bytes());
+            encode_key(2, 0, &mut sample);
+            encode_varint(timestamp as u64, &mut sample);
+            encode_bytes(2, &sample, &mut series);
+
+            encode_bytes(1, &series, &mut request);
+        }
+    }
+    request
+}
+
+pub(super) fn encode_key(field: u64, wire_type: u64, buf: &mut Vec<u8>) {
+    encode_varint((field << 3) | wire_type, buf);
+}
+
+pub(super) fn encode_bytes(field: u64, data: &[u8], buf: &mut Vec<u8>) {
+    encode_key(field, 2, buf);
+    encode_varint(data.len() as u64, buf);
+    buf.extend_from_slice(data);
+}
+
+fn encode_varint(mut value: u64, buf: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grouping_key_encodes_unsafe_values() {
+        assert_eq!(grouping_key("job", "osc-cost"), "job/osc-cost");
+        assert_eq!(
+            grouping_key("instance", "eu-west-2.1_a~"),
+            "instance/eu-west-2.1_a~"
+        );
+        assert_eq!(grouping_key("instance", ""), "instance@base64/=");
+        for value in [".", "..", "a/b", "a b", "a?b", "a#b", "a%20b", "é"] {
+            let key = grouping_key("instance", value);
+            let encoded = key.strip_prefix("instance@base64/").unwrap();
+            assert_eq!(URL_SAFE_NO_PAD.decode(encoded).unwrap(), value.as_bytes());
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use prometheus::core::GenericGauge;
+use prometheus::proto::MetricFamily;
 use prometheus::Gauge;
 use prometheus::Opts;
 use prometheus::Registry;
@@ -41,13 +42,13 @@ struct GaugeState {
     gauge_secondary_opt: Opts,
     name: String,
 }
-pub fn to_prom<T>(
+pub fn to_metric_families<T>(
     value: &T,
     include: Vec<String>,
     primary: CustomLabelKey,
     secondary: CustomLabelKey,
     label_name: String,
-) -> Result<String>
+) -> Result<Vec<MetricFamily>>
 where
     T: Serialize,
 {
@@ -70,10 +71,13 @@ where
             registry.register(Box::new(gauge)).unwrap_or_default();
         }
     }
+    Ok(registry.gather())
+}
+
+pub fn encode(metric_families: &[MetricFamily]) -> Result<String> {
     let mut buffer = Vec::<u8>::new();
     let encoder = TextEncoder::new();
-    let metric_families = registry.gather();
-    let Ok(_) = encoder.encode(&metric_families, &mut buffer) else {
+    let Ok(_) = encoder.encode(metric_families, &mut buffer) else {
         return Err(Error::Message("Can not encode ".to_string()));
     };
     Ok(String::from_utf8(buffer.clone()).unwrap_or_default())
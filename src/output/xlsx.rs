@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use osc_cost::core::{Resource, Resources, HOURS_PER_MONTH};
+use rust_xlsxwriter::{utility::column_number_to_name, Format, Formula, Workbook, Worksheet};
+use serde_json::Value;
+
+use super::csv::{fields, text};
+use super::get_currency;
+
+// One sheet per resource type like the ods output, plus a summary sheet whose totals are
+// SUBTOTAL formulas so that they follow the filtered rows.
+pub fn xlsx(resources: &Resources) -> Result<Vec<u8>, Box<dyn Error>> {
+    let region = resources
+        .resources
+        .first()
+        .and_then(|r| r.region())
+        .unwrap_or_default();
+    let header = Format::new().set_bold();
+    let currency = Format::new().set_num_format(format!(
+        "#,##0.00\"{}\"",
+        get_currency(region).replace('"', "")
+    ));
+    let total = currency.clone().set_bold();
+
+    let mut types = BTreeMap::<&str, Vec<&Resource>>::new();
+    for resource in &resources.resources {
+        types
+            .entry(resource.resource_type())
+            .or_default()
+            .push(resource);
+    }
+
+    let mut summary = Worksheet::new();
+    summary.set_name("Summary")?;
+    for (col, title) in [
+        "Resource Type",
+        "Count",
+        "Total price per hour",
+        "Total price per month",
+        "Total price per year",
+    ]
+    .iter()
+    .enumerate()
+    {
+        summary.write_with_format(0, col as u16, *title, &header)?;
+        summary.set_column_width(col as u16, 22)?;
+    }
+
+    let mut sheets = Vec::new();
+    for (row, (resource_type, resources)) in types.iter().enumerate() {
+        let mut sheet = Worksheet::new();
+        sheet.set_name(*resource_type)?;
+        let mut columns = Vec::<String>::new();
+        for (index, resource) in resources.iter().enumerate() {
+            let row = index as u32 + 1;
+            for (key, value) in fields(resource)? {
+                let col = match columns.iter().position(|column| column == &key) {
+                    Some(col) => col,
+                    None => {
+                        sheet.write_with_format(0, columns.len() as u16, &key, &header)?;
+                        columns.push(key.clone());
+                        columns.len() - 1
+                    }
+                } as u16;
+                match &value {
+                    Value::Null => {}
+                    Value::Bool(value) => {
+                        sheet.write_boolean(row, col, *value)?;
+                    }
+                    Value::Number(_) => {
+                        let number = text(&value).parse::<f64>()?;
+                        match key.starts_with("price_") {
+                            true => sheet.write_number_with_format(row, col, number, &currency)?,
+                            false => sheet.write_number(row, col, number)?,
+                        };
+                    }
+                    value => {
+                        sheet.write_string(row, col, text(value))?;
+                    }
+                }
+            }
+        }
+        let last_row = resources.len() as u32;
+        sheet.autofilter(0, 0, last_row, columns.len().saturating_sub(1) as u16)?;
+        sheet.set_freeze_panes(1, 0)?;
+
+        // SUBTOTAL(103) counts and SUBTOTAL(109) sums the visible rows only
+        let range = |column: &str| {
+            columns
+                .iter()
+                .position(|c| c == column)
+                .map(|col| {
+                    let name = column_number_to_name(col as u16);
+                    format!("'{resource_type}'!{name}2:{name}{}", last_row + 1)
+                })
+                .unwrap_or_else(|| String::from("0"))
+        };
+        // Each aggregate row counts the resources it aggregates
+        let count = match columns.iter().any(|c| c == "count") {
+            true => format!("=SUBTOTAL(109,{})", range("count")),
+            false => format!("=SUBTOTAL(103,{})", range("resource_type")),
+        };
+        let row = row as u32 + 1;
+        summary.write_string(row, 0, *resource_type)?;
+        summary.write_formula(row, 1, Formula::new(count))?;
+        summary.write_formula_with_format(
+            row,
+            2,
+            Formula::new(format!("=SUBTOTAL(109,{})", range("price_per_hour"))),
+            &currency,
+        )?;
+        summary.write_formula_with_format(
+            row,
+            3,
+            Formula::new(format!("=SUBTOTAL(109,{})", range("price_per_month"))),
+            &currency,
+        )?;
+        summary.write_formula_with_format(
+            row,
+            4,
+            // From the price per hour like cost_per_year, the month of a dedicated VM
+            // misses its factor
+            Formula::new(format!("=C{}*{HOURS_PER_MONTH}*12", row + 1)),
+            &currency,
+        )?;
+        sheets.push(sheet);
+    }
+
+    let total_row = types.len() as u32 + 1;
+    summary.write_with_format(total_row, 0, "Total", &header)?;
+    if total_row > 1 {
+        for col in 1..5u16 {
+            let name = column_number_to_name(col);
+            let format = match col {
+                1 => &header,
+                _ => &total,
+            };
+            summary.write_formula_with_format(
+                total_row,
+                col,
+                Formula::new(format!("=SUM({name}2:{name}{total_row})")),
+                format,
+            )?;
+        }
+    }
+
+    let mut workbook = Workbook::new();
+    workbook.push_worksheet(summary);
+    for sheet in sheets {
+        workbook.push_worksheet(sheet);
+    }
+    Ok(workbook.save_to_buffer()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use calamine::{open_workbook_from_rs, Reader, Xlsx};
+    use osc_cost::core::{vms::Vm, volumes::Volume};
+
+    use super::*;
+
+    fn open(resources: &Resources) -> Xlsx<Cursor<Vec<u8>>> {
+        open_workbook_from_rs(Cursor::new(xlsx(resources).unwrap())).unwrap()
+    }
+
+    // Count and price formulas of a summary row
+    fn row(range: &calamine::Range<String>, row: u32) -> Vec<String> {
+        (1..5)
+            .map(|col| range.get_value((row, col)).cloned().unwrap_or_default())
+            .collect()
+    }
+
+    fn resources() -> Resources {
+        let mut resources = Resources {
+            resources: vec![
+                Resource::Vm(Vm {
+                    resource_id: Some(String::from("i-1")),
+                    vm_vcpu: 2,
+                    vm_ram_gb: 4,
+                    price_vcpu_per_hour: 0.1,
+                    price_ram_gb_per_hour: 0.05,
+                    factor_vm_additional_cost: 1.1,
+                    ..Default::default()
+                }),
+                Resource::Volume(Volume {
+                    resource_id: Some(String::from("vol-1")),
+                    price_per_hour: Some(0.5),
+                    ..Default::default()
+                }),
+                Resource::Volume(Volume {
+                    resource_id: Some(String::from("vol-2")),
+                    price_per_hour: Some(0.25),
+                    ..Default::default()
+                }),
+            ],
+        };
+        resources.compute().unwrap();
+        resources
+    }
+
+    #[test]
+    fn sheets_and_summary_formulas() {
+        let mut workbook = open(&resources());
+        assert_eq!(workbook.sheet_names(), ["Summary", "Vm", "Volume"]);
+
+        let volumes = workbook.worksheet_range("Volume").unwrap();
+        let header = volumes.rows().next().unwrap();
+        assert_eq!(header[0].to_string(), "resource_type");
+        assert!(header.iter().any(|cell| *cell == "volume_iops"));
+        assert_eq!(volumes.height(), 3);
+
+        let formulas = workbook.worksheet_formula("Summary").unwrap();
+        assert_eq!(
+            row(&formulas, 1),
+            [
+                "SUBTOTAL(103,'Vm'!A2:A2)",
+                "SUBTOTAL(109,'Vm'!H2:H2)",
+                "SUBTOTAL(109,'Vm'!I2:I2)",
+                "C2*730*12",
+            ]
+        );
+        assert_eq!(
+            row(&formulas, 3),
+            ["SUM(B2:B3)", "SUM(C2:C3)", "SUM(D2:D3)", "SUM(E2:E3)"]
+        );
+    }
+
+    #[test]
+    fn aggregates_count_their_resources() {
+        let mut workbook = open(&resources().aggregate());
+        let formulas = workbook.worksheet_formula("Summary").unwrap();
+        let volumes = workbook.worksheet_range("Volume").unwrap();
+        let header = volumes.rows().next().unwrap();
+        let count = header.iter().position(|cell| *cell == "count").unwrap();
+        let count = column_number_to_name(count as u16);
+        assert_eq!(
+            row(&formulas, 2)[0],
+            format!("SUBTOTAL(109,'Volume'!{count}2:{count}2)")
+        );
+    }
+}
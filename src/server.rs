@@ -2,15 +2,36 @@ use std::sync::Arc;
 
 use axum::{
     extract::State,
+    middleware,
     response::{IntoResponse, Response},
     routing::get,
     Router,
 };
+use axum_server::tls_rustls::RustlsConfig;
 use clap::Parser;
-use osc_cost::oapi::Input;
+use exporter::auth::{require_auth, Auth};
+use exporter::drift::drift_loop;
+use exporter::history::Recorder;
+use exporter::push::{push_loop, PushTargets};
+use log::info;
+use osc_cost::anomaly::AnomalyConfig;
+use osc_cost::core::{digest::Drifts, Resources};
+use osc_cost::history::History;
+use osc_cost::oapi::{Filter, Input};
+use output::prometheus::push::PushConfig;
+use prometheus::proto::MetricFamily;
+use std::net::SocketAddr;
 use std::sync::Mutex;
+use std::time::Duration;
 
-mod output;
+mod exporter;
+// Only the output formats served by the exporter, shared with the CLI
+mod output {
+    pub mod html;
+    pub mod prometheus;
+
+    pub use osc_cost::core::currency::get_currency;
+}
 
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about)]
@@ -23,6 +44,80 @@ struct Args {
     pub aggregate: bool,
     #[arg(long, short = 'n', default_value_t = false)]
     pub need_default_resource: bool,
+    // PEM certificate chain and private key used to serve HTTPS
+    #[arg(long, value_name = "FILE", requires = "tls_key")]
+    pub tls_cert: Option<String>,
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    pub tls_key: Option<String>,
+    // PEM CA bundle used to verify client certificates (mTLS)
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    pub tls_client_ca: Option<String>,
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        env = "OSC_COST_AUTH_TOKEN",
+        hide_env_values = true
+    )]
+    pub auth_token: Option<String>,
+    #[arg(
+        long,
+        value_name = "USER:PASSWORD",
+        env = "OSC_COST_BASIC_AUTH",
+        hide_env_values = true
+    )]
+    pub basic_auth: Option<String>,
+    // Number of resources listed in the dashboard top list
+    #[arg(long, default_value_t = 10)]
+    pub dashboard_top: usize,
+    // Dashboard auto-refresh period in seconds
+    #[arg(long, default_value_t = 300)]
+    pub dashboard_refresh: u64,
+    // Pushgateway base URL, metrics are pushed to <URL>/metrics/job/<JOB>
+    #[arg(long, value_name = "URL")]
+    pub push_gateway: Option<String>,
+    // Prometheus remote-write endpoint
+    #[arg(long, value_name = "URL")]
+    pub remote_write: Option<String>,
+    // OpenTelemetry collector endpoint, OTLP over HTTP with protobuf encoding only (no gRPC),
+    // metrics are sent to <URL>/v1/metrics
+    #[arg(long, value_name = "URL")]
+    pub otlp_endpoint: Option<String>,
+    #[arg(long, default_value = "osc-cost-exporter")]
+    pub push_job: String,
+    #[arg(long)]
+    pub push_instance: Option<String>,
+    #[arg(long, value_name = "NAME: VALUE")]
+    pub push_header: Vec<String>,
+    // Push period in seconds
+    #[arg(long, default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..))]
+    pub push_interval: u64,
+    // Periodically compare the inventory with the consumption digest
+    #[arg(long, default_value_t = false)]
+    pub compute_drift: bool,
+    // Number of past days of consumption compared at each drift computation
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    pub drift_days: u64,
+    // Drift computation period in seconds
+    #[arg(long, default_value_t = 3600, value_parser = clap::value_parser!(u64).range(1..))]
+    pub drift_interval: u64,
+    // SQLite database where the priced resources are appended on refresh
+    #[arg(long, value_name = "FILE", env = "OSC_COST_HISTORY_DB")]
+    pub history_db: Option<String>,
+    // Minimum period in seconds between two records in the history
+    #[arg(long, default_value_t = 3600, value_parser = clap::value_parser!(u64).range(1..))]
+    pub history_interval: u64,
+    // Compare the inventory with the last runs of the history on each scrape
+    #[arg(long, default_value_t = false, requires = "history_db")]
+    pub detect_anomalies: bool,
+    // Number of past runs in the baseline
+    #[arg(long, default_value_t = 10)]
+    pub anomaly_runs: usize,
+    // Minimum deviation from the baseline, in standard deviations
+    #[arg(long, default_value_t = 3.0)]
+    pub anomaly_threshold: f64,
+    // Minimum change from the baseline, in percent
+    #[arg(long, default_value_t = 20.0)]
+    pub anomaly_min_change: f64,
 }
 
 #[derive(Clone)]
@@ -30,47 +125,118 @@ struct AppState {
     input: Arc<Mutex<Input>>,
     aggregate: bool,
     need_default_resource: bool,
+    dashboard_top: usize,
+    dashboard_refresh: u64,
+    // Last drift computed by the drift task, if enabled
+    drifts: Arc<Mutex<Option<Drifts>>>,
+    history: Option<Arc<Mutex<Recorder>>>,
+    anomaly: Option<AnomalyConfig>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
     let args = Args::parse();
 
+    let auth = Auth::new(args.auth_token.clone(), args.basic_auth.clone())?;
+
     let args_profile = args.profile.clone();
     let input = tokio::task::spawn_blocking(move || {
         Input::new(args_profile).expect("Could not configure backend")
     })
     .await?;
 
+    let history = match &args.history_db {
+        Some(path) => Some(Arc::new(Mutex::new(Recorder::new(
+            History::open(path)?,
+            args.history_interval,
+        )))),
+        None => None,
+    };
+
     let state = AppState {
         input: Arc::new(Mutex::new(input)),
         aggregate: args.aggregate,
         need_default_resource: args.need_default_resource,
+        dashboard_top: args.dashboard_top,
+        dashboard_refresh: args.dashboard_refresh,
+        drifts: Arc::new(Mutex::new(None)),
+        history,
+        anomaly: args.detect_anomalies.then_some(AnomalyConfig {
+            runs: args.anomaly_runs,
+            threshold: args.anomaly_threshold,
+            min_change_percent: args.anomaly_min_change,
+        }),
+    };
+
+    if args.compute_drift {
+        tokio::spawn(drift_loop(
+            state.clone(),
+            args.drift_days,
+            Duration::from_secs(args.drift_interval),
+        ));
+    }
+
+    let push_targets = PushTargets {
+        push_gateway: args.push_gateway.clone(),
+        remote_write: args.remote_write.clone(),
+        otlp: args.otlp_endpoint.clone(),
+        config: PushConfig {
+            job: args.push_job.clone(),
+            instance: args.push_instance.clone(),
+            headers: args.push_header.clone(),
+        },
     };
+    if !push_targets.is_empty() {
+        tokio::spawn(push_loop(
+            state.clone(),
+            push_targets,
+            Duration::from_secs(args.push_interval),
+        ));
+    }
 
-    let app = Router::new()
-        .route("/", get(root))
-        .route("/metrics", get(root))
-        .route("/health", get(healhcheck))
-        .with_state(state);
+    let mut app = Router::new()
+        .route("/", get(exporter::dashboard::dashboard))
+        .route("/metrics", get(metrics))
+        .route("/api/resources", get(exporter::api::resources))
+        .route("/api/summary", get(exporter::api::summary))
+        .route("/api/history", get(exporter::history::history));
+    if auth.is_enabled() {
+        app = app.route_layer(middleware::from_fn_with_state(Arc::new(auth), require_auth));
+    }
+    // The liveness probe stays reachable without credentials
+    let app = app.route("/health", get(healhcheck)).with_state(state);
 
-    let listener =
-        tokio::net::TcpListener::bind(args.bind.unwrap_or_else(|| "127.0.0.1:3000".to_string()))
-            .await
-            .unwrap();
-    axum::serve(listener, app).await?;
+    let bind = args.bind.unwrap_or_else(|| "127.0.0.1:3000".to_string());
+    match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => {
+            let config = exporter::tls::server_config(cert, key, args.tls_client_ca.as_deref())?;
+            let addr: SocketAddr = bind.parse()?;
+            info!("serving https on {addr}");
+            axum_server::bind_rustls(addr, RustlsConfig::from_config(Arc::new(config)))
+                .serve(app.into_make_service())
+                .await?;
+        }
+        _ => {
+            let listener = tokio::net::TcpListener::bind(bind).await?;
+            axum::serve(listener, app).await?;
+        }
+    }
 
     Ok(())
 }
 
-async fn root(State(state): State<AppState>) -> Result<Response, String> {
+async fn fetch_resources(state: &AppState, filters: Option<Filter>) -> Result<Resources, String> {
     let local_lock = state.input.clone();
     let need_default_resource = state.need_default_resource;
+    // Filtered inventories are partial and must not end up in the history
+    let full_inventory = filters.is_none();
     let mut resources = match tokio::task::spawn_blocking(move || {
         let mut inputs = local_lock
             .lock()
             .map_err(|e| format!("Could not lock mutex: {e}"))?;
         inputs.need_default_resource = need_default_resource;
+        inputs.filters = filters;
         inputs
             .fetch()
             .map_err(|e| format!("Could not fetch inputs: {e}"))?;
@@ -86,16 +252,54 @@ async fn root(State(state): State<AppState>) -> Result<Response, String> {
 
     resources.compute().map_err(|e| e.to_string())?;
 
+    if let (true, Some(history)) = (full_inventory, &state.history) {
+        exporter::history::record(history, &resources);
+    }
+
+    Ok(resources)
+}
+
+async fn metric_families(state: &AppState) -> Result<Vec<MetricFamily>, String> {
+    let mut resources = fetch_resources(state, None).await?;
+
+    let anomalies = match (&state.anomaly, &state.history) {
+        (Some(config), Some(history)) => {
+            Some(exporter::history::anomalies(history, &resources, config)?)
+        }
+        _ => None,
+    };
+
     if state.aggregate {
         resources = resources.aggregate();
     }
 
+    let mut metric_families =
+        output::prometheus::metric_families(&resources).map_err(|e| e.to_string())?;
+    if let Some(anomalies) = &anomalies {
+        metric_families.extend(
+            output::prometheus::anomaly_metric_families(anomalies).map_err(|e| e.to_string())?,
+        );
+    }
+    let drifts = state
+        .drifts
+        .lock()
+        .map_err(|e| format!("Could not lock drifts: {e}"))?;
+    if let Some(drifts) = drifts.as_ref() {
+        metric_families
+            .extend(output::prometheus::drift_metric_families(drifts).map_err(|e| e.to_string())?);
+    }
+    Ok(metric_families)
+}
+
+async fn metrics(State(state): State<AppState>) -> Result<Response, String> {
+    let metric_families = metric_families(&state).await?;
+
     Ok((
         [(
             http::header::CONTENT_TYPE,
             http::HeaderValue::from_static("text/plain; version=0.0.4; charset=utf-8"),
         )],
-        output::prometheus::prometheus(&resources).unwrap(),
+        output::prometheus::encode(&metric_families).map_err(|e| e.to_string())?,
     )
         .into_response())
 }